@@ -1,4 +1,4 @@
-use input_device::{InputSimulator, SimulationError};
+use input_device::{InputSimulator, PenState, SimulationError};
 
 pub fn main() -> Result<(), SimulationError> {
     let mut sim = InputSimulator::new()?;
@@ -9,10 +9,10 @@ pub fn main() -> Result<(), SimulationError> {
         let theta = theta as f64 / 100.0;
         let x = 2.0 * theta * theta.cos() + center_x as f64;
         let y = 2.0 * theta * theta.sin() + center_y as f64;
-        sim.pen(x as i32, y as i32, 0.5, 0, 0)?;
+        sim.pen(PenState::contact(x as i32, y as i32, 0.5))?;
         std::thread::sleep(std::time::Duration::from_millis(4));
     }
     std::thread::sleep(std::time::Duration::from_millis(100));
-    sim.pen(0, 0, 0.0, 0, 0)?;
+    sim.pen(PenState::out_of_range(0, 0))?;
     Ok(())
 }
@@ -1,16 +1,16 @@
-use input_device::{InputSimulator, SimulationError};
+use input_device::{gestures, InputSimulator, SimulationError};
+use std::time::Duration;
 
 pub fn main() -> Result<(), SimulationError> {
     let mut sim = InputSimulator::new()?;
-    std::thread::sleep(std::time::Duration::from_secs(1));
-    sim.touch_down(0, 300, 300)?;
-    sim.touch_down(1, 600, 300)?;
-    for i in 1..10 {
-        sim.touch_move(0, 300 + i * 10, 300)?;
-        sim.touch_move(1, 600 - i * 10, 300)?;
-        std::thread::sleep(std::time::Duration::from_millis(15));
-    }
-    sim.touch_up(0)?;
-    sim.touch_up(1)?;
+    std::thread::sleep(Duration::from_secs(1));
+    gestures::pinch(
+        &mut sim,
+        (450, 300),
+        300.0,
+        0.4,
+        Duration::from_millis(150),
+        60,
+    )?;
     Ok(())
 }
@@ -0,0 +1,8 @@
+use input_device::{InputSimulator, SimulationError};
+
+pub fn main() -> Result<(), SimulationError> {
+    let mut sim = InputSimulator::new()?;
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    sim.type_text("Hello, World!")?;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::{
+    InputEvent, Key, KeyboardLayout, Monitor, MouseButton, PenState, SimulationError,
+    TouchAttributes, TouchContact,
+};
+
+/// A sink for simulated input.
+///
+/// An [InputSimulator](crate::InputSimulator) forwards every
+/// operation to its backend. The platform backend used by
+/// [InputSimulator::new](crate::InputSimulator::new) emits real
+/// input on the host, but any type implementing this trait can be
+/// passed to [InputSimulator::from_backend](crate::InputSimulator::from_backend)
+/// to route input elsewhere, such as to a remote machine or a log.
+///
+/// Coordinates are in pixels of the virtual screen described by
+/// [get_screen_size](InputBackend::get_screen_size). Implementors
+/// that need to report their own failures can use
+/// [SimulationError::Backend].
+///
+/// Backends must be [Send], since the input they hold down may be
+/// released from a signal handler thread.
+pub trait InputBackend: Send {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError>;
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError>;
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError>;
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError>;
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError>;
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError>;
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError>;
+    /// Start a touch contact. See [TouchAttributes] for the meaning
+    /// of the attributes that are not set.
+    fn touch_down(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError>;
+    fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError>;
+    fn touch_move(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError>;
+    /// Update several touch slots at once.
+    ///
+    /// The default implementation sends the equivalent touch
+    /// operations as one batch with [send](InputBackend::send).
+    /// Backends should deliver the updates together, so that the
+    /// contacts move at the same time.
+    fn touch_frame(&mut self, contacts: &[TouchContact]) -> Result<(), SimulationError> {
+        let events: Vec<InputEvent> = contacts.iter().map(TouchContact::event).collect();
+        self.send(&events)
+    }
+    /// Update the pen. Every field of the state is reported, not
+    /// only the ones that changed.
+    fn pen(&mut self, state: PenState) -> Result<(), SimulationError>;
+    /// Type a single character without going through a keyboard
+    /// layout.
+    ///
+    /// The default implementation reports the character as
+    /// unsupported.
+    fn type_unicode(&mut self, c: char) -> Result<(), SimulationError> {
+        Err(SimulationError::UnsupportedCharacters(vec![c]))
+    }
+    /// Whether [type_unicode](InputBackend::type_unicode) can type
+    /// characters that the keyboard layout cannot produce.
+    ///
+    /// The default implementation returns `false`.
+    fn can_type_unicode(&self) -> bool {
+        false
+    }
+    /// The keyboard layout currently active on the host, if the
+    /// backend can determine it.
+    ///
+    /// The default implementation returns `None`.
+    fn keyboard_layout(&self) -> Result<Option<KeyboardLayout>, SimulationError> {
+        Ok(None)
+    }
+    /// The combined size of the virtual screen space.
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError>;
+    /// The monitors that make up the virtual screen.
+    ///
+    /// The default implementation returns a single monitor covering
+    /// the whole virtual screen.
+    fn monitors(&self) -> Result<Vec<Monitor>, SimulationError> {
+        Ok(vec![Monitor::whole_screen(self.get_screen_size()?)])
+    }
+    /// Where the pointer is on the host, if the backend can
+    /// determine it.
+    ///
+    /// The default implementation returns `None`.
+    fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        Ok(None)
+    }
+    /// The mouse buttons held down on the host, by any device, if
+    /// the backend can determine them.
+    ///
+    /// The default implementation returns `None`.
+    fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        Ok(None)
+    }
+
+    /// Perform a sequence of operations in order.
+    ///
+    /// The default implementation performs each operation
+    /// individually. Backends that can group operations into
+    /// larger atomic units should override this.
+    fn send(&mut self, events: &[InputEvent]) -> Result<(), SimulationError> {
+        for event in events {
+            dispatch(self, event)?;
+        }
+        Ok(())
+    }
+}
+
+/// Perform a single operation on a backend.
+pub(crate) fn dispatch<B: InputBackend + ?Sized>(
+    backend: &mut B,
+    event: &InputEvent,
+) -> Result<(), SimulationError> {
+    match *event {
+        InputEvent::MoveMouseAbs { x, y } => backend.move_mouse_abs(x, y),
+        InputEvent::MoveMouseRel { x, y } => backend.move_mouse_rel(x, y),
+        InputEvent::MouseDown(button) => backend.mouse_down(button),
+        InputEvent::MouseUp(button) => backend.mouse_up(button),
+        InputEvent::Wheel { x, y } => backend.wheel(x, y),
+        InputEvent::KeyDown(key) => backend.key_down(key),
+        InputEvent::KeyUp(key) => backend.key_up(key),
+        InputEvent::TouchDown {
+            slot,
+            x,
+            y,
+            attributes,
+        } => backend.touch_down(slot, x, y, attributes),
+        InputEvent::TouchUp { slot } => backend.touch_up(slot),
+        InputEvent::TouchMove {
+            slot,
+            x,
+            y,
+            attributes,
+        } => backend.touch_move(slot, x, y, attributes),
+        InputEvent::Pen(state) => backend.pen(state),
+        InputEvent::TypeUnicode(c) => backend.type_unicode(c),
+    }
+}
@@ -0,0 +1,215 @@
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[cfg(target_os = "linux")]
+use crate::{DeviceIdentity, PenGeometry, PenMode, TouchscreenGeometry, UinputDevice};
+use crate::{InputBackend, InputSimulator, KeyboardLayout, PlatformImpl, SimulationError};
+
+/// A class of virtual input device.
+///
+/// Operations on a class that was not enabled in the
+/// [InputSimulatorBuilder] fail with a `DeviceDisabled` error.
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceClass {
+    /// Key presses and releases.
+    Keyboard,
+    /// Pointer motion, mouse buttons and the mouse wheel.
+    Mouse,
+    /// Multi-touch touchscreen contacts.
+    Touch,
+    /// Pen or stylus input.
+    Pen,
+}
+
+/// A builder for an [InputSimulator] with non-default settings.
+///
+/// ```rust,no_run
+/// use input_device::{DeviceClass, InputSimulator};
+///
+/// # fn main() -> Result<(), input_device::SimulationError> {
+/// let simulator = InputSimulator::builder()
+///     .screen_size(1920, 1080)
+///     .devices([DeviceClass::Keyboard, DeviceClass::Mouse])
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InputSimulatorBuilder {
+    pub(crate) screen_size: Option<(i32, i32)>,
+    pub(crate) devices: HashSet<DeviceClass>,
+    layout: Option<KeyboardLayout>,
+    motion_rate: u32,
+    #[cfg(target_os = "linux")]
+    identities: HashMap<UinputDevice, DeviceIdentity>,
+    #[cfg(target_os = "linux")]
+    pub(crate) outputs: HashMap<UinputDevice, String>,
+    #[cfg(target_os = "linux")]
+    pub(crate) touch_geometry: TouchscreenGeometry,
+    #[cfg(target_os = "linux")]
+    pub(crate) pen_geometry: PenGeometry,
+    #[cfg(target_os = "linux")]
+    pub(crate) pen_mode: PenMode,
+}
+
+impl Default for InputSimulatorBuilder {
+    fn default() -> Self {
+        Self {
+            screen_size: None,
+            devices: DeviceClass::iter().collect(),
+            layout: None,
+            motion_rate: InputSimulator::DEFAULT_MOTION_RATE,
+            #[cfg(target_os = "linux")]
+            identities: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            outputs: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            touch_geometry: TouchscreenGeometry::default(),
+            #[cfg(target_os = "linux")]
+            pen_geometry: PenGeometry::default(),
+            #[cfg(target_os = "linux")]
+            pen_mode: PenMode::default(),
+        }
+    }
+}
+
+impl InputSimulatorBuilder {
+    /// Create a builder with default settings, in which every
+    /// device class is enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable exactly the given device classes, disabling all
+    /// others.
+    ///
+    /// On Linux, each class corresponds to one or more uinput
+    /// devices, which are only created if the class is enabled.
+    pub fn devices(mut self, devices: impl IntoIterator<Item = DeviceClass>) -> Self {
+        self.devices = devices.into_iter().collect();
+        self
+    }
+
+    /// Enable a device class.
+    pub fn enable(mut self, device: DeviceClass) -> Self {
+        self.devices.insert(device);
+        self
+    }
+
+    /// Disable a device class.
+    pub fn disable(mut self, device: DeviceClass) -> Self {
+        self.devices.remove(&device);
+        self
+    }
+
+    /// Set the keyboard layout used to type text.
+    ///
+    /// By default, the layout active on the host is used if the
+    /// backend can report it (currently only with an X11 connection
+    /// on Linux), and [KeyboardLayout::us_qwerty] otherwise.
+    pub fn layout(mut self, layout: KeyboardLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Set how many pointer updates per second smooth motions emit.
+    /// The default is 120.
+    pub fn motion_rate(mut self, rate: u32) -> Self {
+        self.motion_rate = rate;
+        self
+    }
+
+    /// Set the name, IDs and physical path of one of the uinput
+    /// devices.
+    #[cfg(target_os = "linux")]
+    pub fn device_identity(mut self, device: UinputDevice, identity: DeviceIdentity) -> Self {
+        self.identities.insert(device, identity);
+        self
+    }
+
+    /// Restrict the touchscreen or the pen to a single RandR output,
+    /// such as `HDMI-1`, as a real touchscreen built into a monitor
+    /// would be.
+    ///
+    /// The device is mapped to the output through its XInput
+    /// coordinate transformation matrix, which is updated whenever
+    /// RandR reports a change, and coordinates are still given in
+    /// pixels of the virtual screen. This requires an X11 connection,
+    /// and is ignored for the other devices.
+    #[cfg(target_os = "linux")]
+    pub fn device_output(mut self, device: UinputDevice, output: impl Into<String>) -> Self {
+        self.outputs.insert(device, output.into());
+        self
+    }
+
+    /// Set the axis ranges, resolution, number of slots and pressure
+    /// levels of the uinput touchscreen.
+    #[cfg(target_os = "linux")]
+    pub fn touchscreen_geometry(mut self, geometry: TouchscreenGeometry) -> Self {
+        self.touch_geometry = geometry;
+        self
+    }
+
+    /// Set the axis ranges, resolution, pressure levels and tilt range
+    /// of the uinput pen.
+    #[cfg(target_os = "linux")]
+    pub fn pen_geometry(mut self, geometry: PenGeometry) -> Self {
+        self.pen_geometry = geometry;
+        self
+    }
+
+    /// Make the uinput pen a pen display or an opaque tablet. The
+    /// default is [PenMode::Display].
+    #[cfg(target_os = "linux")]
+    pub fn pen_mode(mut self, mode: PenMode) -> Self {
+        self.pen_mode = mode;
+        self
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn identity(&self, device: UinputDevice) -> DeviceIdentity {
+        self.identities
+            .get(&device)
+            .cloned()
+            .unwrap_or_else(|| DeviceIdentity::default_for(device))
+    }
+
+    pub(crate) fn has_device(&self, device: DeviceClass) -> bool {
+        self.devices.contains(&device)
+    }
+
+    /// Use a fixed screen size instead of querying the display
+    /// server.
+    ///
+    /// On Linux, this also means that no X11 connection is made,
+    /// so the simulator works without a running X server (for
+    /// example on Wayland, on a bare console or in a container).
+    /// Other platforms currently ignore this setting.
+    pub fn screen_size(mut self, width: i32, height: i32) -> Self {
+        self.screen_size = Some((width, height));
+        self
+    }
+
+    /// Create the platform backend with these settings, without
+    /// wrapping it in an [InputSimulator].
+    pub fn build_backend(&self) -> Result<Box<dyn InputBackend>, SimulationError> {
+        Ok(Box::new(PlatformImpl::new(self)?))
+    }
+
+    /// Create the input simulator.
+    pub fn build(&self) -> Result<InputSimulator, SimulationError> {
+        let mut simulator = InputSimulator::from_backend(self.build_backend()?);
+        simulator.set_motion_rate(self.motion_rate);
+        match &self.layout {
+            Some(layout) => simulator.set_layout(layout.clone()),
+            None => {
+                simulator.refresh_layout()?;
+            }
+        }
+        Ok(simulator)
+    }
+}
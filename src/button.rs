@@ -0,0 +1,29 @@
+use strum_macros::EnumIter;
+
+/// A mouse button.
+///
+/// The variants follow the mouse buttons defined by evdev. Windows
+/// only supports the first five, and macOS reports the buttons after
+/// the middle one by number. [MouseButton::Raw] can send other
+/// buttons on Linux.
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// The first side button, which browsers use to go back.
+    Side,
+    /// The second side button, which browsers use to go forward.
+    Extra,
+    Forward,
+    Back,
+    Task,
+    /// A raw evdev button code, for buttons that have no variant.
+    ///
+    /// Only Linux supports raw buttons, and only the codes from
+    /// `BTN_0` to `BTN_9` and in the `BTN_MOUSE` range are
+    /// registered on the virtual mice. It is not yielded by the
+    /// iterator over the variants.
+    #[strum(disabled)]
+    Raw(u16),
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::Key;
+
+/// A combination of keys that are held together, such as a keyboard
+/// shortcut.
+///
+/// The keys are pressed in order and released in reverse order, so
+/// modifiers should come first. Chords can be parsed from strings of
+/// [Key] names joined by `+`, such as `"Ctrl+Alt+Delete"` or
+/// `"Cmd+Shift+T"`, and are displayed in the same format using the
+/// canonical key names.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    keys: Vec<Key>,
+}
+
+/// An error returned when parsing a [Chord] fails.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseChordError {
+    #[error("The chord contains no keys")]
+    Empty,
+    #[error("Unknown key name: {0:?}")]
+    UnknownKey(String),
+}
+
+impl Chord {
+    /// Create a chord from keys in the order in which they are
+    /// pressed.
+    pub fn new(keys: impl IntoIterator<Item = Key>) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+
+    /// The keys of the chord, in the order in which they are pressed.
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+}
+
+impl From<Key> for Chord {
+    fn from(key: Key) -> Self {
+        Self::new([key])
+    }
+}
+
+impl FromStr for Chord {
+    type Err = ParseChordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(ParseChordError::Empty);
+        }
+        let keys = s
+            .split('+')
+            .map(|name| {
+                let name = name.trim();
+                name.parse()
+                    .map_err(|_| ParseChordError::UnknownKey(name.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            write!(f, "{key}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_aliases_and_displays_canonical_names() {
+        let chord: Chord = "ctrl + Shift+t".parse().unwrap();
+        assert_eq!(chord.keys(), [Key::LeftCtrl, Key::LeftShift, Key::T]);
+        assert_eq!(chord.to_string(), "LeftCtrl+LeftShift+T");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let chord = Chord::new([Key::LeftMeta, Key::RightAlt, Key::Delete, Key::Num1]);
+        assert_eq!(chord.to_string().parse::<Chord>(), Ok(chord));
+    }
+
+    #[test]
+    fn rejects_empty_and_unknown_keys() {
+        assert_eq!("  ".parse::<Chord>(), Err(ParseChordError::Empty));
+        assert_eq!(
+            "Ctrl+Nope".parse::<Chord>(),
+            Err(ParseChordError::UnknownKey("Nope".to_string()))
+        );
+        assert_eq!(
+            "Ctrl+".parse::<Chord>(),
+            Err(ParseChordError::UnknownKey(String::new()))
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{Key, MouseButton, PenState, TouchAttributes};
+
+/// A single input operation.
+///
+/// Each variant mirrors one of the methods on
+/// [InputSimulator](crate::InputSimulator), with the same
+/// arguments and the same coordinate conventions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    MoveMouseAbs {
+        x: i32,
+        y: i32,
+    },
+    MoveMouseRel {
+        x: i32,
+        y: i32,
+    },
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    Wheel {
+        x: i32,
+        y: i32,
+    },
+    KeyDown(Key),
+    KeyUp(Key),
+    TouchDown {
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    },
+    TouchUp {
+        slot: i32,
+    },
+    TouchMove {
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    },
+    Pen(PenState),
+    TypeUnicode(char),
+}
@@ -0,0 +1,155 @@
+//! Multi-touch gestures built on the touch slot API.
+//!
+//! Every gesture touches down, animates its contacts and lifts them
+//! again, blocking until it is done. Contacts use the slots starting
+//! at zero, so other touches should not be active at the same time.
+//! Moving gestures update their contacts `fps` times per second, and
+//! each update is sent as a single touch frame.
+
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use crate::{InputSimulator, SimulationError, TouchContact};
+
+/// The distance between neighbouring fingers, in pixels.
+const FINGER_SPACING: f64 = 60.0;
+
+/// Spread or pinch two fingers around `center`.
+///
+/// The fingers start `distance` pixels apart on a horizontal line,
+/// and end `distance * scale` pixels apart, so a `scale` above one
+/// zooms in and a `scale` below one zooms out.
+pub fn pinch(
+    simulator: &mut InputSimulator,
+    center: (i32, i32),
+    distance: f64,
+    scale: f64,
+    duration: Duration,
+    fps: u32,
+) -> Result<(), SimulationError> {
+    animate(simulator, 2, duration, fps, |finger, t| {
+        let radius = distance / 2.0 * (1.0 + (scale - 1.0) * t);
+        let side = if finger == 0 { -1.0 } else { 1.0 };
+        offset(center, side * radius, 0.0)
+    })
+}
+
+/// Rotate two fingers around `center` by `angle` degrees, clockwise
+/// on screen.
+///
+/// The fingers start on a horizontal line, `distance` pixels apart.
+pub fn rotate(
+    simulator: &mut InputSimulator,
+    center: (i32, i32),
+    distance: f64,
+    angle: f64,
+    duration: Duration,
+    fps: u32,
+) -> Result<(), SimulationError> {
+    animate(simulator, 2, duration, fps, |finger, t| {
+        let theta = angle.to_radians() * t + finger as f64 * PI;
+        let radius = distance / 2.0;
+        offset(center, radius * theta.cos(), radius * theta.sin())
+    })
+}
+
+/// Swipe `fingers` fingers side by side, starting around `center`
+/// and moving by `(dx, dy)` pixels.
+pub fn swipe(
+    simulator: &mut InputSimulator,
+    center: (i32, i32),
+    fingers: u32,
+    (dx, dy): (i32, i32),
+    duration: Duration,
+    fps: u32,
+) -> Result<(), SimulationError> {
+    let (dx, dy) = (dx as f64, dy as f64);
+    // Line the fingers up across the direction of the swipe.
+    let length = dx.hypot(dy).max(1.0);
+    let (across_x, across_y) = (-dy / length, dx / length);
+    animate(simulator, fingers, duration, fps, |finger, t| {
+        let spread = spread(finger, fingers);
+        offset(
+            center,
+            dx * t + across_x * spread,
+            dy * t + across_y * spread,
+        )
+    })
+}
+
+/// Touch and hold `center` with one finger for `duration`.
+pub fn long_press(
+    simulator: &mut InputSimulator,
+    center: (i32, i32),
+    duration: Duration,
+) -> Result<(), SimulationError> {
+    tap(simulator, center, 1, duration)
+}
+
+/// Tap with `fingers` fingers side by side around `center`, holding
+/// them down for `duration`.
+pub fn tap(
+    simulator: &mut InputSimulator,
+    center: (i32, i32),
+    fingers: u32,
+    duration: Duration,
+) -> Result<(), SimulationError> {
+    animate(simulator, fingers, duration, 0, |finger, _| {
+        offset(center, spread(finger, fingers), 0.0)
+    })
+}
+
+/// The offset of a finger from the middle of a row of fingers.
+fn spread(finger: u32, fingers: u32) -> f64 {
+    (finger as f64 - (fingers as f64 - 1.0) / 2.0) * FINGER_SPACING
+}
+
+fn offset(center: (i32, i32), dx: f64, dy: f64) -> (i32, i32) {
+    (
+        (center.0 as f64 + dx).round() as i32,
+        (center.1 as f64 + dy).round() as i32,
+    )
+}
+
+/// Touch down `fingers` contacts, move them along `position` over
+/// `duration` and lift them.
+///
+/// `position` maps a finger and the progress of the gesture, from
+/// zero to one, to a position on the screen. With an `fps` of zero,
+/// the contacts are held still.
+fn animate(
+    simulator: &mut InputSimulator,
+    fingers: u32,
+    duration: Duration,
+    fps: u32,
+    position: impl Fn(u32, f64) -> (i32, i32),
+) -> Result<(), SimulationError> {
+    let down: Vec<_> = (0..fingers)
+        .map(|finger| {
+            let (x, y) = position(finger, 0.0);
+            TouchContact::down(finger as i32, x, y)
+        })
+        .collect();
+    simulator.touch_frame(&down)?;
+
+    let start = Instant::now();
+    let frames = (duration.as_secs_f64() * fps as f64).round() as u32;
+    for i in 1..=frames {
+        let t = i as f64 / frames as f64;
+        if let Some(wait) = duration.mul_f64(t).checked_sub(start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+        let moves: Vec<_> = (0..fingers)
+            .map(|finger| {
+                let (x, y) = position(finger, t);
+                TouchContact::moved(finger as i32, x, y)
+            })
+            .collect();
+        simulator.touch_frame(&moves)?;
+    }
+    if let Some(wait) = duration.checked_sub(start.elapsed()) {
+        std::thread::sleep(wait);
+    }
+    let lifts: Vec<_> = (0..fingers as i32).map(TouchContact::up).collect();
+    simulator.touch_frame(&lifts)
+}
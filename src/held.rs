@@ -0,0 +1,476 @@
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, Weak};
+
+use crate::backend::{dispatch, InputBackend};
+use crate::{
+    InputEvent, Key, KeyboardLayout, Monitor, MouseButton, PenState, SimulationError,
+    TouchAttributes, TouchContact,
+};
+
+/// Everything a simulator currently has pressed on the host.
+#[derive(Debug, Default)]
+struct HeldInput {
+    /// Held keys, in the order in which they were pressed.
+    keys: Vec<Key>,
+    buttons: Vec<MouseButton>,
+    /// Slots of the touch contacts that are down.
+    touches: Vec<i32>,
+    /// The last state of the pen while it is in range.
+    pen: Option<PenState>,
+}
+
+/// How an attempted operation turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Succeeded,
+    /// The operation failed, but may have partially taken effect.
+    Failed,
+    /// The operation was refused before anything reached the host.
+    Rejected,
+}
+
+impl Outcome {
+    fn of(result: &Result<(), SimulationError>) -> Self {
+        match result {
+            Ok(()) => Outcome::Succeeded,
+            Err(err) if err.is_rejection() => Outcome::Rejected,
+            Err(_) => Outcome::Failed,
+        }
+    }
+
+    /// The outcome for each operation of a batch. A batch may fail
+    /// after some of its operations have taken effect, so a rejected
+    /// batch counts as failed.
+    fn of_batch(result: &Result<(), SimulationError>) -> Self {
+        match result {
+            Ok(()) => Outcome::Succeeded,
+            Err(_) => Outcome::Failed,
+        }
+    }
+}
+
+impl HeldInput {
+    /// Update the state after an operation was attempted.
+    ///
+    /// Presses are recorded even if the operation failed, since they
+    /// may have partially taken effect, unless it was rejected
+    /// outright. Releases are recorded once they have succeeded, or
+    /// once they are rejected, since retrying them cannot help.
+    fn observe(&mut self, event: &InputEvent, outcome: Outcome) {
+        let attempted = outcome != Outcome::Rejected;
+        match *event {
+            InputEvent::KeyDown(key) if attempted => add(&mut self.keys, key),
+            InputEvent::MouseDown(button) if attempted => add(&mut self.buttons, button),
+            InputEvent::TouchDown { slot, .. } if attempted => add(&mut self.touches, slot),
+            InputEvent::Pen(state) => {
+                if state.in_range {
+                    if attempted {
+                        self.pen = Some(state);
+                    }
+                } else if outcome != Outcome::Failed {
+                    self.pen = None;
+                }
+            }
+            _ if outcome == Outcome::Failed => {}
+            InputEvent::KeyUp(key) => remove(&mut self.keys, key),
+            InputEvent::MouseUp(button) => remove(&mut self.buttons, button),
+            InputEvent::TouchUp { slot } => remove(&mut self.touches, slot),
+            _ => {}
+        }
+    }
+
+    /// The operations that release everything, in the order in which
+    /// they should be performed.
+    fn releases(&self) -> Vec<InputEvent> {
+        let touches = self
+            .touches
+            .iter()
+            .map(|&slot| InputEvent::TouchUp { slot });
+        let pen = self.pen.map(|state| {
+            InputEvent::Pen(PenState {
+                in_range: false,
+                contact: false,
+                barrel: false,
+                barrel2: false,
+                ..state
+            })
+        });
+        let buttons = self
+            .buttons
+            .iter()
+            .map(|&button| InputEvent::MouseUp(button));
+        let keys = self.keys.iter().rev().map(|&key| InputEvent::KeyUp(key));
+        touches.chain(pen).chain(buttons).chain(keys).collect()
+    }
+}
+
+fn add<T: PartialEq>(held: &mut Vec<T>, item: T) {
+    if !held.contains(&item) {
+        held.push(item);
+    }
+}
+
+fn remove<T: PartialEq>(held: &mut Vec<T>, item: T) {
+    held.retain(|held| *held != item);
+}
+
+/// A backend wrapper that keeps track of what is held down, so that
+/// it can be released later.
+pub(crate) struct TrackingBackend {
+    backend: Box<dyn InputBackend>,
+    held: HeldInput,
+    /// Where the simulator last put the pointer, if known.
+    pointer: Option<(i32, i32)>,
+}
+
+impl TrackingBackend {
+    pub(crate) fn new(backend: Box<dyn InputBackend>) -> Self {
+        Self {
+            backend,
+            held: HeldInput::default(),
+            pointer: None,
+        }
+    }
+
+    /// Where the simulator last put the pointer, or `None` if it has
+    /// not positioned it yet.
+    pub(crate) fn pointer(&self) -> Option<(i32, i32)> {
+        self.pointer
+    }
+
+    fn observe(&mut self, event: &InputEvent, outcome: Outcome) {
+        self.held.observe(event, outcome);
+        let succeeded = outcome == Outcome::Succeeded;
+        match *event {
+            InputEvent::MoveMouseAbs { x, y } if succeeded => self.pointer = Some((x, y)),
+            InputEvent::MoveMouseRel { x, y } if succeeded => {
+                self.pointer = self.pointer.map(|(px, py)| (px + x, py + y));
+            }
+            _ => {}
+        }
+    }
+
+    fn perform(&mut self, event: InputEvent) -> Result<(), SimulationError> {
+        let result = dispatch(&mut *self.backend, &event);
+        self.observe(&event, Outcome::of(&result));
+        result
+    }
+
+    /// Release everything that is held down.
+    ///
+    /// Every release is attempted even if some fail, and the first
+    /// error is returned.
+    pub(crate) fn release_all(&mut self) -> Result<(), SimulationError> {
+        let mut result = Ok(());
+        for event in self.held.releases() {
+            if let Err(err) = self.perform(event) {
+                log::warn!("Failed to release {event:?}: {err}");
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl InputBackend for TrackingBackend {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.perform(InputEvent::MoveMouseAbs { x, y })
+    }
+
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.perform(InputEvent::MoveMouseRel { x, y })
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.perform(InputEvent::MouseDown(button))
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.perform(InputEvent::MouseUp(button))
+    }
+
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.perform(InputEvent::Wheel { x, y })
+    }
+
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.perform(InputEvent::KeyDown(key))
+    }
+
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.perform(InputEvent::KeyUp(key))
+    }
+
+    fn touch_down(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.perform(InputEvent::TouchDown {
+            slot,
+            x,
+            y,
+            attributes,
+        })
+    }
+
+    fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
+        self.perform(InputEvent::TouchUp { slot })
+    }
+
+    fn touch_move(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.perform(InputEvent::TouchMove {
+            slot,
+            x,
+            y,
+            attributes,
+        })
+    }
+
+    fn pen(&mut self, state: PenState) -> Result<(), SimulationError> {
+        self.perform(InputEvent::Pen(state))
+    }
+
+    fn touch_frame(&mut self, contacts: &[TouchContact]) -> Result<(), SimulationError> {
+        let result = self.backend.touch_frame(contacts);
+        let outcome = Outcome::of_batch(&result);
+        for contact in contacts {
+            self.observe(&contact.event(), outcome);
+        }
+        result
+    }
+
+    fn type_unicode(&mut self, c: char) -> Result<(), SimulationError> {
+        self.perform(InputEvent::TypeUnicode(c))
+    }
+
+    fn can_type_unicode(&self) -> bool {
+        self.backend.can_type_unicode()
+    }
+
+    fn keyboard_layout(&self) -> Result<Option<KeyboardLayout>, SimulationError> {
+        self.backend.keyboard_layout()
+    }
+
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
+        self.backend.get_screen_size()
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>, SimulationError> {
+        self.backend.monitors()
+    }
+
+    fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        self.backend.mouse_position()
+    }
+
+    fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        self.backend.mouse_buttons()
+    }
+
+    fn send(&mut self, events: &[InputEvent]) -> Result<(), SimulationError> {
+        let result = self.backend.send(events);
+        let outcome = Outcome::of_batch(&result);
+        for event in events {
+            self.observe(event, outcome);
+        }
+        result
+    }
+}
+
+/// A tracking backend shared between a simulator and the signal
+/// handler.
+pub(crate) type SharedBackend = Arc<Mutex<TrackingBackend>>;
+
+/// Lock a shared backend, ignoring poisoning. A panic while the lock
+/// was held must not prevent releasing the held input afterwards.
+pub(crate) fn lock(backend: &Mutex<TrackingBackend>) -> std::sync::MutexGuard<'_, TrackingBackend> {
+    backend.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The backends to release when the process receives a termination
+/// signal.
+static SIGNAL_TARGETS: Mutex<Vec<Weak<Mutex<TrackingBackend>>>> = Mutex::new(Vec::new());
+
+/// Release the input held by `backend` when the process is
+/// interrupted or terminated, installing the process-wide signal
+/// handler if needed.
+pub(crate) fn release_on_signal(backend: &SharedBackend) -> Result<(), SimulationError> {
+    static HANDLER: OnceLock<Result<(), String>> = OnceLock::new();
+    HANDLER
+        .get_or_init(install_handler)
+        .clone()
+        .map_err(SimulationError::SignalHandler)?;
+
+    let mut targets = SIGNAL_TARGETS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    targets.retain(|target| target.strong_count() > 0);
+    targets.push(Arc::downgrade(backend));
+    Ok(())
+}
+
+/// The write end of the pipe that the signal handler reports
+/// received signals through.
+#[cfg(unix)]
+static SIGNAL_PIPE: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(-1);
+
+/// Handle SIGINT, SIGTERM and SIGHUP on a dedicated thread, which
+/// the signal handler wakes with the number of the signal.
+#[cfg(unix)]
+fn install_handler() -> Result<(), String> {
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    // SAFETY: the pipe was just created, and nothing else owns its
+    // read end.
+    let mut signals = unsafe { std::fs::File::from_raw_fd(fds[0]) };
+    SIGNAL_PIPE.store(fds[1], std::sync::atomic::Ordering::SeqCst);
+    std::thread::Builder::new()
+        .name("input-device signal handler".to_string())
+        .spawn(move || {
+            let mut signal = [0];
+            if signals.read_exact(&mut signal).is_ok() {
+                handle_signal(signal[0].into());
+            }
+        })
+        .map_err(|err| err.to_string())?;
+
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        // SAFETY: the action is fully initialized before it is
+        // installed, and the handler only makes async-signal-safe
+        // calls.
+        let result = unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut())
+        };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    let signal = signal as u8;
+    // SAFETY: write is async-signal-safe, and the buffer outlives the
+    // call.
+    unsafe {
+        libc::write(
+            SIGNAL_PIPE.load(std::sync::atomic::Ordering::SeqCst),
+            (&signal as *const u8).cast(),
+            1,
+        );
+    }
+}
+
+#[cfg(windows)]
+fn install_handler() -> Result<(), String> {
+    // Windows has no signal numbers, so exit as though interrupted by
+    // SIGINT.
+    ctrlc::set_handler(|| handle_signal(2)).map_err(|err| err.to_string())
+}
+
+/// Release the held input and exit with the conventional status for a
+/// process killed by `signal`.
+fn handle_signal(signal: i32) {
+    let targets = SIGNAL_TARGETS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    for backend in targets.iter().filter_map(Weak::upgrade) {
+        let _ = lock(&backend).release_all();
+    }
+    std::process::exit(128 + signal);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceClass;
+
+    fn touch_down(slot: i32) -> InputEvent {
+        InputEvent::TouchDown {
+            slot,
+            x: 0,
+            y: 0,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    #[test]
+    fn releases_touches_then_pen_then_buttons_then_keys_in_reverse() {
+        let mut held = HeldInput::default();
+        for event in [
+            InputEvent::KeyDown(Key::LeftCtrl),
+            InputEvent::MouseDown(MouseButton::Left),
+            InputEvent::KeyDown(Key::A),
+            InputEvent::Pen(PenState::contact(1, 2, 0.5)),
+            touch_down(3),
+        ] {
+            held.observe(&event, Outcome::Succeeded);
+        }
+        assert_eq!(
+            held.releases(),
+            [
+                InputEvent::TouchUp { slot: 3 },
+                InputEvent::Pen(PenState {
+                    pressure: 0.5,
+                    ..PenState::out_of_range(1, 2)
+                }),
+                InputEvent::MouseUp(MouseButton::Left),
+                InputEvent::KeyUp(Key::A),
+                InputEvent::KeyUp(Key::LeftCtrl),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_press_is_held_until_released() {
+        let mut held = HeldInput::default();
+        held.observe(&InputEvent::KeyDown(Key::A), Outcome::Failed);
+        held.observe(&InputEvent::KeyUp(Key::A), Outcome::Failed);
+        assert_eq!(held.releases(), [InputEvent::KeyUp(Key::A)]);
+        held.observe(&InputEvent::KeyUp(Key::A), Outcome::Succeeded);
+        assert!(held.releases().is_empty());
+    }
+
+    #[test]
+    fn rejected_press_is_not_held() {
+        let mut held = HeldInput::default();
+        held.observe(&InputEvent::KeyDown(Key::A), Outcome::Rejected);
+        held.observe(&touch_down(42), Outcome::Rejected);
+        assert!(held.releases().is_empty());
+    }
+
+    #[test]
+    fn rejected_release_is_dropped() {
+        let mut held = HeldInput::default();
+        held.observe(&touch_down(0), Outcome::Failed);
+        held.observe(&InputEvent::TouchUp { slot: 0 }, Outcome::Rejected);
+        assert!(held.releases().is_empty());
+    }
+
+    #[test]
+    fn device_disabled_is_a_rejection() {
+        let result = Err(SimulationError::DeviceDisabled(DeviceClass::Keyboard));
+        assert_eq!(Outcome::of(&result), Outcome::Rejected);
+        assert_eq!(Outcome::of_batch(&result), Outcome::Failed);
+    }
+}
@@ -1,4 +1,6 @@
-use strum_macros::EnumIter;
+use std::fmt;
+
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
 
 /// A physical keyboard key.
 ///
@@ -15,18 +17,40 @@ use strum_macros::EnumIter;
 /// to work based on the physical key positions rather than
 /// the characters they produce, such as games or custom
 /// input handling.
-#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// The variants cover every key defined in Linux's
+/// `input-event-codes.h`, named after the evdev codes. Not every key
+/// is available on every platform. [Key::Raw] can send codes that
+/// have no variant.
+///
+/// Keys can be converted to and from their names, which are the
+/// variant names. Parsing ignores ASCII case and also accepts common
+/// aliases, such as "Ctrl", "Shift", "Return", "Cmd", "Super" or
+/// "1" for [Key::Num1]. Modifier aliases refer to the left-hand key.
+#[derive(EnumIter, EnumString, IntoStaticStr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[strum(ascii_case_insensitive)]
 pub enum Key {
+    #[strum(to_string = "Esc", serialize = "Escape")]
     Esc,
+    #[strum(to_string = "Num1", serialize = "1")]
     Num1,
+    #[strum(to_string = "Num2", serialize = "2")]
     Num2,
+    #[strum(to_string = "Num3", serialize = "3")]
     Num3,
+    #[strum(to_string = "Num4", serialize = "4")]
     Num4,
+    #[strum(to_string = "Num5", serialize = "5")]
     Num5,
+    #[strum(to_string = "Num6", serialize = "6")]
     Num6,
+    #[strum(to_string = "Num7", serialize = "7")]
     Num7,
+    #[strum(to_string = "Num8", serialize = "8")]
     Num8,
+    #[strum(to_string = "Num9", serialize = "9")]
     Num9,
+    #[strum(to_string = "Num0", serialize = "0")]
     Num0,
     Minus,
     Equal,
@@ -44,7 +68,9 @@ pub enum Key {
     P,
     LeftBrace,
     RightBrace,
+    #[strum(to_string = "Enter", serialize = "Return")]
     Enter,
+    #[strum(to_string = "LeftCtrl", serialize = "Ctrl", serialize = "Control")]
     LeftCtrl,
     A,
     S,
@@ -58,6 +84,7 @@ pub enum Key {
     Semicolon,
     Apostrophe,
     Grave,
+    #[strum(to_string = "LeftShift", serialize = "Shift")]
     LeftShift,
     Backslash,
     Z,
@@ -72,6 +99,7 @@ pub enum Key {
     Slash,
     RightShift,
     KpAsterisk,
+    #[strum(to_string = "LeftAlt", serialize = "Alt", serialize = "Option")]
     LeftAlt,
     Space,
     CapsLock,
@@ -114,17 +142,24 @@ pub enum Key {
     KpEnter,
     RightCtrl,
     KpSlash,
+    #[strum(to_string = "SysRq", serialize = "PrintScreen")]
     SysRq,
+    #[strum(to_string = "RightAlt", serialize = "AltGr")]
     RightAlt,
+    LineFeed,
     Home,
     Up,
+    #[strum(to_string = "PageUp", serialize = "PgUp")]
     PageUp,
     Left,
     Right,
     End,
     Down,
+    #[strum(to_string = "PageDown", serialize = "PgDn")]
     PageDown,
+    #[strum(to_string = "Insert", serialize = "Ins")]
     Insert,
+    #[strum(to_string = "Delete", serialize = "Del")]
     Delete,
     Macro,
     Mute,
@@ -134,44 +169,423 @@ pub enum Key {
     KpEqual,
     KpPlusMinus,
     Pause,
+    Scale,
     KpComma,
+    #[strum(to_string = "Hanguel", serialize = "Hangeul")]
     Hanguel,
     Hanja,
     Yen,
     /// Corresponds to Command on macOS
+    #[strum(
+        to_string = "LeftMeta",
+        serialize = "Meta",
+        serialize = "Cmd",
+        serialize = "Command",
+        serialize = "Super",
+        serialize = "Win"
+    )]
     LeftMeta,
     RightMeta,
     Compose,
     Stop,
+    Again,
+    Props,
+    Undo,
+    Front,
+    Copy,
+    Open,
+    Paste,
+    Find,
+    Cut,
     Help,
+    Menu,
     Calc,
+    Setup,
     Sleep,
     WakeUp,
+    File,
+    SendFile,
+    DeleteFile,
+    Xfer,
+    Prog1,
+    Prog2,
+    Www,
+    MsDos,
+    #[strum(to_string = "ScreenLock", serialize = "Coffee")]
     ScreenLock,
+    #[strum(to_string = "RotateDisplay", serialize = "Direction")]
+    RotateDisplay,
+    CycleWindows,
     Mail,
     Bookmarks,
     Computer,
     Back,
     Forward,
+    CloseCD,
+    EjectCD,
+    EjectCloseCD,
     NextSong,
     PlayPause,
     PreviousSong,
     StopCD,
+    Record,
+    Rewind,
+    Phone,
+    Iso,
+    Config,
     Homepage,
     Refresh,
+    Exit,
+    Move,
+    Edit,
+    ScrollUp,
+    ScrollDown,
+    KpLeftParen,
+    KpRightParen,
+    New,
+    Redo,
     F13,
     F14,
     F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
     F23,
+    F24,
+    PlayCD,
+    PauseCD,
+    Prog3,
+    Prog4,
+    #[strum(to_string = "AllApplications", serialize = "Dashboard")]
+    AllApplications,
+    Suspend,
+    Close,
+    Play,
+    FastForward,
+    BassBoost,
+    Print,
+    Hp,
     Camera,
+    Sound,
+    Question,
+    Email,
+    Chat,
     Search,
+    Connect,
+    Finance,
+    Sport,
+    Shop,
+    AltErase,
+    Cancel,
     BrightnessDown,
     BrightnessUp,
     Media,
     SwitchVideoMode,
+    KbdIllumToggle,
+    KbdIllumDown,
+    KbdIllumUp,
+    Send,
+    Reply,
+    ForwardMail,
+    Save,
+    Documents,
     Battery,
+    Bluetooth,
     Wlan,
+    Uwb,
+    VideoNext,
+    VideoPrev,
+    BrightnessCycle,
+    #[strum(to_string = "BrightnessZero", serialize = "BrightnessAuto")]
     BrightnessZero,
+    DisplayOff,
+    #[strum(to_string = "Wwan", serialize = "Wimax")]
+    Wwan,
+    RfKill,
+    MicMute,
+    Ok,
+    Select,
+    Goto,
+    Clear,
+    Power2,
+    OptionKey,
+    Info,
+    Time,
+    Vendor,
+    Archive,
+    Program,
+    Channel,
+    Favorites,
+    Epg,
+    Pvr,
+    Mhp,
+    Language,
+    Title,
+    Subtitle,
+    Angle,
+    #[strum(to_string = "FullScreen", serialize = "Zoom")]
+    FullScreen,
+    Mode,
+    Keyboard,
+    #[strum(to_string = "AspectRatio", serialize = "Screen")]
+    AspectRatio,
+    Pc,
+    Tv,
+    Tv2,
+    Vcr,
+    Vcr2,
+    Sat,
+    Sat2,
+    Cd,
+    Tape,
+    Radio,
+    Tuner,
+    Player,
+    Text,
     Dvd,
+    Aux,
+    Mp3,
+    Audio,
+    Video,
+    Directory,
+    List,
+    Memo,
+    Calendar,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    ChannelUp,
+    ChannelDown,
+    First,
+    Last,
+    Ab,
+    Next,
+    Restart,
+    Slow,
+    Shuffle,
+    Break,
+    Previous,
+    Digits,
+    Teen,
+    Twen,
+    VideoPhone,
+    Games,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    WordProcessor,
+    Editor,
+    Spreadsheet,
+    GraphicsEditor,
+    Presentation,
+    Database,
+    News,
+    VoiceMail,
+    AddressBook,
+    Messenger,
+    #[strum(to_string = "DisplayToggle", serialize = "BrightnessToggle")]
+    DisplayToggle,
+    SpellCheck,
+    LogOff,
+    Dollar,
+    Euro,
+    FrameBack,
+    FrameForward,
+    ContextMenu,
+    MediaRepeat,
+    TenChannelsUp,
+    TenChannelsDown,
+    Images,
+    NotificationCenter,
+    PickupPhone,
+    HangupPhone,
+    LinkPhone,
+    DelEol,
+    DelEos,
+    InsLine,
+    DelLine,
+    Fn,
     FnEsc,
+    FnF1,
+    FnF2,
+    FnF3,
+    FnF4,
+    FnF5,
+    FnF6,
+    FnF7,
+    FnF8,
+    FnF9,
+    FnF10,
+    FnF11,
+    FnF12,
+    Fn1,
+    Fn2,
+    FnD,
+    FnE,
+    FnF,
+    FnS,
+    FnB,
+    FnRightShift,
+    BrlDot1,
+    BrlDot2,
+    BrlDot3,
+    BrlDot4,
+    BrlDot5,
+    BrlDot6,
+    BrlDot7,
+    BrlDot8,
+    BrlDot9,
+    BrlDot10,
+    Numeric0,
+    Numeric1,
+    Numeric2,
+    Numeric3,
+    Numeric4,
+    Numeric5,
+    Numeric6,
+    Numeric7,
+    Numeric8,
+    Numeric9,
+    NumericStar,
+    NumericPound,
+    NumericA,
+    NumericB,
+    NumericC,
+    NumericD,
+    CameraFocus,
+    WpsButton,
+    TouchpadToggle,
+    TouchpadOn,
+    TouchpadOff,
+    CameraZoomIn,
+    CameraZoomOut,
+    CameraUp,
+    CameraDown,
+    CameraLeft,
+    CameraRight,
+    AttendantOn,
+    AttendantOff,
+    AttendantToggle,
+    LightsToggle,
+    AlsToggle,
+    RotateLockToggle,
+    RefreshRateToggle,
+    ButtonConfig,
+    TaskManager,
+    Journal,
+    ControlPanel,
+    AppSelect,
+    ScreenSaver,
+    VoiceCommand,
+    Assistant,
+    KbdLayoutNext,
+    EmojiPicker,
+    Dictate,
+    BrightnessMin,
+    BrightnessMax,
+    KbdInputAssistPrev,
+    KbdInputAssistNext,
+    KbdInputAssistPrevGroup,
+    KbdInputAssistNextGroup,
+    KbdInputAssistAccept,
+    KbdInputAssistCancel,
+    RightUp,
+    RightDown,
+    LeftUp,
+    LeftDown,
+    RootMenu,
+    MediaTopMenu,
+    Numeric11,
+    Numeric12,
+    AudioDesc,
+    ThreeDMode,
+    NextFavorite,
+    StopRecord,
+    PauseRecord,
+    Vod,
+    Unmute,
+    FastReverse,
+    SlowReverse,
+    Data,
+    OnscreenKeyboard,
+    PrivacyScreenToggle,
+    SelectiveScreenshot,
+    NextElement,
+    PreviousElement,
+    AutopilotEngageToggle,
+    MarkWaypoint,
+    Sos,
+    NavChart,
+    FishingChart,
+    SingleRangeRadar,
+    DualRangeRadar,
+    RadarOverlay,
+    TraditionalSonar,
+    ClearVuSonar,
+    SideVuSonar,
+    NavInfo,
+    BrightnessMenu,
+    Macro1,
+    Macro2,
+    Macro3,
+    Macro4,
+    Macro5,
+    Macro6,
+    Macro7,
+    Macro8,
+    Macro9,
+    Macro10,
+    Macro11,
+    Macro12,
+    Macro13,
+    Macro14,
+    Macro15,
+    Macro16,
+    Macro17,
+    Macro18,
+    Macro19,
+    Macro20,
+    Macro21,
+    Macro22,
+    Macro23,
+    Macro24,
+    Macro25,
+    Macro26,
+    Macro27,
+    Macro28,
+    Macro29,
+    Macro30,
+    MacroRecordStart,
+    MacroRecordStop,
+    MacroPresetCycle,
+    MacroPreset1,
+    MacroPreset2,
+    MacroPreset3,
+    KbdLcdMenu1,
+    KbdLcdMenu2,
+    KbdLcdMenu3,
+    KbdLcdMenu4,
+    KbdLcdMenu5,
+    /// A raw platform key code, for keys that have no variant.
+    ///
+    /// This is an evdev key code on Linux, a scan code on Windows
+    /// and a virtual key code on macOS. It is not yielded by
+    /// [KeyIter], and is displayed as `Raw(code)` but cannot be
+    /// parsed from a string.
+    #[strum(disabled)]
+    Raw(u16),
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Raw(code) => write!(f, "Raw({code})"),
+            key => f.write_str(key.into()),
+        }
+    }
 }
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use crate::Key;
+
+/// The modifier keys that have to be held while pressing a key to
+/// produce a character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    /// AltGr, also known as the third level chooser.
+    pub alt_gr: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        alt_gr: false,
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        alt_gr: false,
+    };
+    pub const ALT_GR: Modifiers = Modifiers {
+        shift: false,
+        alt_gr: true,
+    };
+
+    /// The physical keys used to hold these modifiers, in the order
+    /// in which they are pressed, with AltGr on [Key::RightAlt]. See
+    /// [KeyboardLayout::modifier_keys] for layouts that put AltGr
+    /// elsewhere.
+    pub fn keys(self) -> impl Iterator<Item = Key> {
+        [
+            self.shift.then_some(Key::LeftShift),
+            self.alt_gr.then_some(Key::RightAlt),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// A key press, along with the modifiers held while it happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyStroke {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyStroke {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+}
+
+/// A mapping from characters to the [KeyStroke]s that produce them.
+///
+/// Since [Key] describes physical keys, typing text requires
+/// knowing which keyboard layout the host is using. This type
+/// describes such a layout. A few common layouts are built in, and
+/// custom ones can be assembled with [KeyboardLayout::insert].
+///
+/// Dead keys are not part of the built-in layouts, so characters
+/// that can only be composed with them are reported as unsupported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    strokes: HashMap<char, KeyStroke>,
+    /// The key held for [Modifiers::alt_gr].
+    alt_gr: Key,
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self {
+            strokes: HashMap::new(),
+            alt_gr: Key::RightAlt,
+        }
+    }
+}
+
+/// Keys shared by every built-in layout.
+const COMMON: &[(Key, char)] = &[(Key::Space, ' '), (Key::Enter, '\n'), (Key::Tab, '\t')];
+
+const LETTERS: &[Key] = &[
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+impl KeyboardLayout {
+    /// Create a layout that maps no characters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map a character to a key stroke, replacing any previous
+    /// mapping for it.
+    pub fn insert(&mut self, c: char, stroke: KeyStroke) {
+        self.strokes.insert(c, stroke);
+    }
+
+    /// The key stroke that produces the given character, if any.
+    pub fn stroke(&self, c: char) -> Option<KeyStroke> {
+        self.strokes.get(&c).copied()
+    }
+
+    /// The key that acts as AltGr on this layout. This is
+    /// [Key::RightAlt] unless it was changed.
+    pub fn alt_gr_key(&self) -> Key {
+        self.alt_gr
+    }
+
+    /// Change the key that acts as AltGr.
+    pub fn set_alt_gr_key(&mut self, key: Key) {
+        self.alt_gr = key;
+    }
+
+    /// The physical keys used to hold the given modifiers on this
+    /// layout, in the order in which they are pressed.
+    pub fn modifier_keys(&self, modifiers: Modifiers) -> impl Iterator<Item = Key> {
+        [
+            modifiers.shift.then_some(Key::LeftShift),
+            modifiers.alt_gr.then_some(self.alt_gr),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// The characters of `text` that this layout cannot produce,
+    /// in order of appearance and without duplicates.
+    pub fn unsupported_chars(&self, text: &str) -> Vec<char> {
+        let mut unsupported = Vec::new();
+        for c in text.chars() {
+            if !self.strokes.contains_key(&c) && !unsupported.contains(&c) {
+                unsupported.push(c);
+            }
+        }
+        unsupported
+    }
+
+    /// Build a layout from rows of `(key, base, shifted)` characters,
+    /// plus characters reached through AltGr. A `'\0'` marks an
+    /// unused level.
+    fn from_table(
+        letters: &[(Key, char)],
+        rows: &[(Key, char, char)],
+        alt_gr: &[(Key, char)],
+    ) -> Self {
+        let mut layout = Self::new();
+        for &(key, c) in COMMON {
+            layout.insert(c, KeyStroke::new(key, Modifiers::NONE));
+        }
+        for &(key, c) in letters {
+            layout.insert(c, KeyStroke::new(key, Modifiers::NONE));
+            for upper in c.to_uppercase() {
+                layout.insert(upper, KeyStroke::new(key, Modifiers::SHIFT));
+            }
+        }
+        for &(key, base, shifted) in rows {
+            if base != '\0' {
+                layout.insert(base, KeyStroke::new(key, Modifiers::NONE));
+            }
+            if shifted != '\0' {
+                layout.insert(shifted, KeyStroke::new(key, Modifiers::SHIFT));
+            }
+        }
+        for &(key, c) in alt_gr {
+            layout.insert(c, KeyStroke::new(key, Modifiers::ALT_GR));
+        }
+        layout
+    }
+
+    /// Letters printed on the keys of a QWERTY keyboard.
+    fn qwerty_letters() -> Vec<(Key, char)> {
+        LETTERS.iter().copied().zip('a'..='z').collect()
+    }
+
+    /// The US QWERTY layout.
+    pub fn us_qwerty() -> Self {
+        Self::from_table(
+            &Self::qwerty_letters(),
+            &[
+                (Key::Grave, '`', '~'),
+                (Key::Num1, '1', '!'),
+                (Key::Num2, '2', '@'),
+                (Key::Num3, '3', '#'),
+                (Key::Num4, '4', '$'),
+                (Key::Num5, '5', '%'),
+                (Key::Num6, '6', '^'),
+                (Key::Num7, '7', '&'),
+                (Key::Num8, '8', '*'),
+                (Key::Num9, '9', '('),
+                (Key::Num0, '0', ')'),
+                (Key::Minus, '-', '_'),
+                (Key::Equal, '=', '+'),
+                (Key::LeftBrace, '[', '{'),
+                (Key::RightBrace, ']', '}'),
+                (Key::Backslash, '\\', '|'),
+                (Key::Semicolon, ';', ':'),
+                (Key::Apostrophe, '\'', '"'),
+                (Key::Comma, ',', '<'),
+                (Key::Dot, '.', '>'),
+                (Key::Slash, '/', '?'),
+            ],
+            &[],
+        )
+    }
+
+    /// The UK QWERTY layout.
+    pub fn uk_qwerty() -> Self {
+        Self::from_table(
+            &Self::qwerty_letters(),
+            &[
+                (Key::Grave, '`', '¬'),
+                (Key::Num1, '1', '!'),
+                (Key::Num2, '2', '"'),
+                (Key::Num3, '3', '£'),
+                (Key::Num4, '4', '$'),
+                (Key::Num5, '5', '%'),
+                (Key::Num6, '6', '^'),
+                (Key::Num7, '7', '&'),
+                (Key::Num8, '8', '*'),
+                (Key::Num9, '9', '('),
+                (Key::Num0, '0', ')'),
+                (Key::Minus, '-', '_'),
+                (Key::Equal, '=', '+'),
+                (Key::LeftBrace, '[', '{'),
+                (Key::RightBrace, ']', '}'),
+                (Key::Semicolon, ';', ':'),
+                (Key::Apostrophe, '\'', '@'),
+                (Key::Backslash, '#', '~'),
+                (Key::IntlBackslash, '\\', '|'),
+                (Key::Comma, ',', '<'),
+                (Key::Dot, '.', '>'),
+                (Key::Slash, '/', '?'),
+            ],
+            &[(Key::Num4, '€')],
+        )
+    }
+
+    /// The German QWERTZ layout.
+    pub fn de_qwertz() -> Self {
+        let letters: Vec<_> = Self::qwerty_letters()
+            .into_iter()
+            .map(|(key, c)| match c {
+                'y' => (key, 'z'),
+                'z' => (key, 'y'),
+                _ => (key, c),
+            })
+            .collect();
+        Self::from_table(
+            &letters,
+            &[
+                (Key::Grave, '\0', '°'),
+                (Key::Num1, '1', '!'),
+                (Key::Num2, '2', '"'),
+                (Key::Num3, '3', '§'),
+                (Key::Num4, '4', '$'),
+                (Key::Num5, '5', '%'),
+                (Key::Num6, '6', '&'),
+                (Key::Num7, '7', '/'),
+                (Key::Num8, '8', '('),
+                (Key::Num9, '9', ')'),
+                (Key::Num0, '0', '='),
+                (Key::Minus, 'ß', '?'),
+                (Key::LeftBrace, 'ü', 'Ü'),
+                (Key::RightBrace, '+', '*'),
+                (Key::Semicolon, 'ö', 'Ö'),
+                (Key::Apostrophe, 'ä', 'Ä'),
+                (Key::Backslash, '#', '\''),
+                (Key::IntlBackslash, '<', '>'),
+                (Key::Comma, ',', ';'),
+                (Key::Dot, '.', ':'),
+                (Key::Slash, '-', '_'),
+            ],
+            &[
+                (Key::Num2, '²'),
+                (Key::Num3, '³'),
+                (Key::Num7, '{'),
+                (Key::Num8, '['),
+                (Key::Num9, ']'),
+                (Key::Num0, '}'),
+                (Key::Minus, '\\'),
+                (Key::Q, '@'),
+                (Key::E, '€'),
+                (Key::RightBrace, '~'),
+                (Key::IntlBackslash, '|'),
+                (Key::M, 'µ'),
+            ],
+        )
+    }
+
+    /// The French AZERTY layout.
+    pub fn fr_azerty() -> Self {
+        let letters: Vec<_> = Self::qwerty_letters()
+            .into_iter()
+            .filter(|&(_, c)| c != 'm')
+            .map(|(key, c)| match c {
+                'a' => (key, 'q'),
+                'q' => (key, 'a'),
+                'w' => (key, 'z'),
+                'z' => (key, 'w'),
+                _ => (key, c),
+            })
+            .chain([(Key::Semicolon, 'm')])
+            .collect();
+        Self::from_table(
+            &letters,
+            &[
+                (Key::Grave, '²', '\0'),
+                (Key::Num1, '&', '1'),
+                (Key::Num2, 'é', '2'),
+                (Key::Num3, '"', '3'),
+                (Key::Num4, '\'', '4'),
+                (Key::Num5, '(', '5'),
+                (Key::Num6, '-', '6'),
+                (Key::Num7, 'è', '7'),
+                (Key::Num8, '_', '8'),
+                (Key::Num9, 'ç', '9'),
+                (Key::Num0, 'à', '0'),
+                (Key::Minus, ')', '°'),
+                (Key::Equal, '=', '+'),
+                (Key::RightBrace, '$', '£'),
+                (Key::Apostrophe, 'ù', '%'),
+                (Key::Backslash, '*', 'µ'),
+                (Key::IntlBackslash, '<', '>'),
+                (Key::M, ',', '?'),
+                (Key::Comma, ';', '.'),
+                (Key::Dot, ':', '/'),
+                (Key::Slash, '!', '§'),
+            ],
+            &[
+                (Key::Num3, '#'),
+                (Key::Num4, '{'),
+                (Key::Num5, '['),
+                (Key::Num6, '|'),
+                (Key::Num8, '\\'),
+                (Key::Num9, '^'),
+                (Key::Num0, '@'),
+                (Key::Minus, ']'),
+                (Key::Equal, '}'),
+                (Key::E, '€'),
+                (Key::RightBrace, '¤'),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn us_qwerty_types_printable_ascii() {
+        let layout = KeyboardLayout::us_qwerty();
+        let ascii: String = (' '..='~').collect();
+        assert!(layout.unsupported_chars(&ascii).is_empty());
+        assert_eq!(
+            layout.stroke('a'),
+            Some(KeyStroke::new(Key::A, Modifiers::NONE))
+        );
+        assert_eq!(
+            layout.stroke('A'),
+            Some(KeyStroke::new(Key::A, Modifiers::SHIFT))
+        );
+        assert_eq!(
+            layout.stroke('"'),
+            Some(KeyStroke::new(Key::Apostrophe, Modifiers::SHIFT))
+        );
+    }
+
+    #[test]
+    fn regional_layouts_move_letters() {
+        let de = KeyboardLayout::de_qwertz();
+        assert_eq!(de.stroke('z').map(|stroke| stroke.key), Some(Key::Y));
+        assert_eq!(
+            de.stroke('€'),
+            Some(KeyStroke::new(Key::E, Modifiers::ALT_GR))
+        );
+        let fr = KeyboardLayout::fr_azerty();
+        assert_eq!(fr.stroke('a').map(|stroke| stroke.key), Some(Key::Q));
+        assert_eq!(
+            fr.stroke('M'),
+            Some(KeyStroke::new(Key::Semicolon, Modifiers::SHIFT))
+        );
+        assert_eq!(
+            fr.stroke('1'),
+            Some(KeyStroke::new(Key::Num1, Modifiers::SHIFT))
+        );
+    }
+
+    #[test]
+    fn unused_levels_are_not_mapped() {
+        let de = KeyboardLayout::de_qwertz();
+        assert_eq!(de.stroke('\0'), None);
+    }
+
+    #[test]
+    fn unsupported_chars_are_unique_and_ordered() {
+        let layout = KeyboardLayout::us_qwerty();
+        assert_eq!(layout.unsupported_chars("é€a€é"), ['é', '€']);
+    }
+
+    #[test]
+    fn modifier_keys_are_pressed_shift_first() {
+        let modifiers = Modifiers {
+            shift: true,
+            alt_gr: true,
+        };
+        assert!(modifiers.keys().eq([Key::LeftShift, Key::RightAlt]));
+    }
+
+    #[test]
+    fn layouts_can_move_alt_gr() {
+        let mut layout = KeyboardLayout::new();
+        assert!(layout.modifier_keys(Modifiers::ALT_GR).eq([Key::RightAlt]));
+        layout.set_alt_gr_key(Key::RightMeta);
+        assert!(layout.modifier_keys(Modifiers::ALT_GR).eq([Key::RightMeta]));
+    }
+}
@@ -11,32 +11,60 @@
 //! - [`InputSimulator`]: The core struct for simulating input events.
 //! - [`Key`]: An enum representing physical keyboard keys.
 //! - [`KeyIter`]: An iterator over all possible [`Key`] variants.
+//! - [`MouseButton`]: An enum representing mouse buttons.
+//! - [`Chord`]: A key combination such as `Ctrl+Alt+Delete`, parsed from a string.
+//! - [`KeyboardLayout`]: Maps characters to the [`Key`]s that produce them.
+//! - [`InputSimulatorBuilder`]: Configures an [`InputSimulator`] before creating it.
+//! - [`InputBackend`]: The trait implemented by every input sink, including
+//!   the platform backends. Custom backends can be plugged into an
+//!   [`InputSimulator`].
+//! - [`InputEvent`]: A typed representation of every simulated operation.
+//! - [`gestures`]: Pinch, rotate, swipe, long-press and multi-finger tap gestures.
+//! - [`EventLog`]: The events captured by a recording simulator.
 //! - [`SimulationError`]: Errors returned when simulation operations fail.
-//! These error variants differ across platforms.
+//!   These error variants differ across platforms.
 //!
 //! ## Current Features
 //!
-//! - Move the mouse to an absolute position or relative to the current position.
-//! - Simulate left, middle, and right mouse button presses and releases.
+//! - Move the mouse to an absolute position or relative to the current position,
+//!   either instantly or smoothly along a [`Path`].
+//! - Simulate mouse button presses, releases and clicks, including the side buttons
+//!   and raw evdev buttons on Linux.
 //! - Scroll horizontally and vertically using the mouse wheel.
-//! - Press and release keyboard keys individually.
-//! - Query the full virtual screen size for multi-monitor setups.
+//! - Press and release keyboard keys individually, or as chords such as `Ctrl+Shift+T`.
+//! - Type text using a [`KeyboardLayout`] that maps characters to keys, falling
+//!   back to arbitrary Unicode characters on X11.
+//! - Read the active keyboard layout from the X server on Linux.
+//! - Query the full virtual screen size for multi-monitor setups, list
+//!   the individual [`Monitor`]s through RandR on Linux, and move the
+//!   mouse, touch contacts and pen relative to a monitor.
+//! - Move several touch contacts in the same frame with [`TouchContact`]s.
+//! - Give touch contacts a pressure, contact size, orientation and tool
+//!   with [`TouchAttributes`].
+//! - Simulate a hovering or touching pen with barrel buttons and an eraser
+//!   using [`PenState`].
+//! - Query where the pointer is and which mouse buttons are held on the host.
+//! - Send batches of typed [`InputEvent`]s in a single call.
+//! - Record simulated input in memory instead of emitting it, for testing.
+//! - Run without a display server on Linux by supplying the screen size.
+//! - Release held input automatically on drop, or on termination signals.
+//! - Choose which virtual devices are created, and how they identify themselves on Linux.
 //!
 //! ## Example
 //!
-//! ```rust
-//! use input_device::{InputDeviceSimulator, Key};
+//! ```rust,no_run
+//! use input_device::{InputSimulator, Key};
 //!
 //! fn main() -> Result<(), input_device::SimulationError> {
-//!     let mut simulator = InputDeviceSimulator::new()?;
-//!     std::thread::sleep(std::Duration::from_secs(1));
+//!     let mut simulator = InputSimulator::new()?;
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
 //!
 //!     simulator.move_mouse_abs(500, 500)?;
 //!     simulator.left_mouse_down()?;
-//!     std::thread::sleep(std::Duration::from_secs(1));
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
 //!     simulator.left_mouse_up()?;
 //!     simulator.key_down(Key::A)?;
-//!     std::thread::sleep(std::Duration::from_secs(1));
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
 //!     simulator.key_up(Key::A)?;
 //!
 //!     Ok(())
@@ -50,8 +78,37 @@ mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
+mod backend;
+mod builder;
+mod button;
+mod chord;
+mod event;
+pub mod gestures;
+mod held;
 mod keys;
+mod layout;
+mod monitor;
+mod motion;
+mod pen;
+mod recording;
+mod touch;
+pub use backend::InputBackend;
+pub use builder::{DeviceClass, InputSimulatorBuilder};
+pub use button::MouseButton;
+pub use chord::{Chord, ParseChordError};
+pub use event::*;
 pub use keys::*;
+pub use layout::{KeyStroke, KeyboardLayout, Modifiers};
+pub use monitor::{Monitor, Rotation};
+pub use motion::Path;
+pub use pen::{PenState, PenTool};
+pub use recording::{EventLog, RecordedEvent};
+pub use touch::{TouchAttributes, TouchContact, TouchPhase, TouchTool};
+
+use held::{SharedBackend, TrackingBackend};
+use recording::RecordingBackend;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "linux")]
 pub use linux::*;
@@ -69,79 +126,418 @@ pub use windows::*;
 ///
 /// Semantics may differ between platforms. Known
 /// differences are documented.
-pub struct InputSimulator(PlatformImpl);
+///
+/// The simulator keeps track of the keys, mouse buttons and touch
+/// contacts it is holding down and whether the pen is in range, and
+/// releases them when it is dropped. See [`InputSimulator::release_all`] and
+/// [`InputSimulator::release_on_signal`].
+pub struct InputSimulator {
+    backend: SharedBackend,
+    layout: KeyboardLayout,
+    motion_rate: u32,
+}
 
 impl InputSimulator {
+    /// The default number of pointer updates per second emitted by
+    /// smooth motions.
+    pub(crate) const DEFAULT_MOTION_RATE: u32 = 120;
+
     /// Create a new input simulator.
     pub fn new() -> Result<Self, SimulationError> {
-        Ok(Self(PlatformImpl::new()?))
+        InputSimulatorBuilder::new().build()
+    }
+
+    /// Create a builder to configure a new input simulator.
+    pub fn builder() -> InputSimulatorBuilder {
+        InputSimulatorBuilder::new()
+    }
+
+    /// Create the backend used by [`InputSimulator::new`], which
+    /// emits real input on the current platform.
+    ///
+    /// This is useful for custom backends that wrap the platform
+    /// backend, for example to log events before forwarding them.
+    pub fn platform_backend() -> Result<Box<dyn InputBackend>, SimulationError> {
+        InputSimulatorBuilder::new().build_backend()
+    }
+
+    /// Create an input simulator that forwards every operation
+    /// to the given backend.
+    ///
+    /// The simulator assumes the US QWERTY layout when typing text.
+    pub fn from_backend(backend: Box<dyn InputBackend>) -> Self {
+        Self {
+            backend: Arc::new(Mutex::new(TrackingBackend::new(backend))),
+            layout: KeyboardLayout::us_qwerty(),
+            motion_rate: Self::DEFAULT_MOTION_RATE,
+        }
+    }
+
+    fn backend(&self) -> MutexGuard<'_, TrackingBackend> {
+        held::lock(&self.backend)
+    }
+
+    /// Release every key, mouse button and touch contact that this
+    /// simulator is holding down, and take the pen out of range.
+    ///
+    /// Every release is attempted even if some of them fail, in
+    /// which case the first error is returned.
+    pub fn release_all(&mut self) -> Result<(), SimulationError> {
+        self.backend().release_all()
+    }
+
+    /// Also release everything this simulator holds down when the
+    /// process receives SIGINT, SIGTERM or SIGHUP (Ctrl-C or a
+    /// closed console on Windows), and then exit the process with
+    /// status 128 plus the signal number.
+    ///
+    /// This installs a process-wide handler, which replaces any
+    /// handler installed by the application. On Windows, it fails if
+    /// one was already installed with the `ctrlc` crate. The handler
+    /// stops tracking the simulator once it is dropped.
+    pub fn release_on_signal(&self) -> Result<(), SimulationError> {
+        held::release_on_signal(&self.backend)
+    }
+
+    /// Create an input simulator that emits nothing and instead
+    /// records every operation in the returned [`EventLog`].
+    ///
+    /// No devices are created and no platform resources are
+    /// required, which makes this suitable for unit tests. The
+    /// reported screen size is always 1920x1080.
+    pub fn recording() -> (Self, EventLog) {
+        let (backend, log) = RecordingBackend::new();
+        (Self::from_backend(Box::new(backend)), log)
     }
 
     pub fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.0.move_mouse_abs(x, y)
+        self.backend().move_mouse_abs(x, y)
     }
 
     pub fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.0.move_mouse_rel(x, y)
+        self.backend().move_mouse_rel(x, y)
+    }
+
+    /// Move the mouse to a point relative to the top left corner of
+    /// a monitor.
+    ///
+    /// Other coordinates can be converted with [`Monitor::to_screen`].
+    pub fn move_mouse_on(
+        &mut self,
+        monitor: &Monitor,
+        x: i32,
+        y: i32,
+    ) -> Result<(), SimulationError> {
+        let (x, y) = monitor.to_screen(x, y);
+        self.move_mouse_abs(x, y)
+    }
+
+    /// Move the mouse to an absolute position along a [`Path`],
+    /// taking `duration` to get there.
+    ///
+    /// The motion starts where the simulator last put the pointer.
+    /// If it has not positioned the pointer yet, the pointer jumps
+    /// straight to the target. This blocks until the motion is done.
+    pub fn move_mouse_smooth(
+        &mut self,
+        x: i32,
+        y: i32,
+        duration: Duration,
+        path: Path,
+    ) -> Result<(), SimulationError> {
+        let Some(from) = self.backend().pointer() else {
+            return self.move_mouse_abs(x, y);
+        };
+        let points = path.points(from, (x, y), motion::steps(duration, self.motion_rate));
+        self.follow(&points, duration, |simulator, (x, y)| {
+            simulator.move_mouse_abs(x, y)
+        })
+    }
+
+    /// Move the mouse by an offset along a [`Path`], taking
+    /// `duration` to get there, using relative motion only.
+    ///
+    /// This blocks until the motion is done.
+    pub fn move_mouse_rel_smooth(
+        &mut self,
+        x: i32,
+        y: i32,
+        duration: Duration,
+        path: Path,
+    ) -> Result<(), SimulationError> {
+        let points = path.points((0, 0), (x, y), motion::steps(duration, self.motion_rate));
+        let mut last = (0, 0);
+        self.follow(&points, duration, |simulator, point| {
+            let (dx, dy) = (point.0 - last.0, point.1 - last.1);
+            last = point;
+            simulator.move_mouse_rel(dx, dy)
+        })
+    }
+
+    /// Set how many pointer updates per second smooth motions emit.
+    pub fn set_motion_rate(&mut self, rate: u32) {
+        self.motion_rate = rate.max(1);
+    }
+
+    /// Visit `points` at even intervals over `duration`.
+    fn follow(
+        &mut self,
+        points: &[(i32, i32)],
+        duration: Duration,
+        mut step: impl FnMut(&mut Self, (i32, i32)) -> Result<(), SimulationError>,
+    ) -> Result<(), SimulationError> {
+        let start = Instant::now();
+        for (i, &point) in points.iter().enumerate() {
+            let due = duration.mul_f64((i + 1) as f64 / points.len() as f64);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            step(self, point)?;
+        }
+        Ok(())
+    }
+
+    pub fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.backend().mouse_down(button)
+    }
+
+    pub fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.backend().mouse_up(button)
+    }
+
+    /// Press and release a mouse button `count` times in a row, for
+    /// example twice for a double click.
+    pub fn click(&mut self, button: MouseButton, count: u32) -> Result<(), SimulationError> {
+        for _ in 0..count {
+            self.mouse_down(button)?;
+            self.mouse_up(button)?;
+        }
+        Ok(())
     }
 
     pub fn left_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.0.left_mouse_down()
+        self.mouse_down(MouseButton::Left)
     }
 
     pub fn middle_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.0.middle_mouse_down()
+        self.mouse_down(MouseButton::Middle)
     }
 
     pub fn right_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.0.right_mouse_down()
+        self.mouse_down(MouseButton::Right)
     }
 
     pub fn left_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.0.left_mouse_up()
+        self.mouse_up(MouseButton::Left)
     }
 
     pub fn middle_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.0.middle_mouse_up()
+        self.mouse_up(MouseButton::Middle)
     }
 
     pub fn right_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.0.right_mouse_up()
+        self.mouse_up(MouseButton::Right)
     }
 
     pub fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.0.wheel(x, y)
+        self.backend().wheel(x, y)
     }
 
     pub fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
-        self.0.key_down(key)
+        self.backend().key_down(key)
     }
 
     pub fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
-        self.0.key_up(key)
+        self.backend().key_up(key)
+    }
+
+    /// Press every key of a chord in order, leaving them held.
+    pub fn press_chord(&mut self, chord: &Chord) -> Result<(), SimulationError> {
+        for &key in chord.keys() {
+            self.backend().key_down(key)?;
+        }
+        Ok(())
+    }
+
+    /// Release every key of a chord in reverse order.
+    pub fn release_chord(&mut self, chord: &Chord) -> Result<(), SimulationError> {
+        for &key in chord.keys().iter().rev() {
+            self.backend().key_up(key)?;
+        }
+        Ok(())
+    }
+
+    /// Press and then release a chord, such as a keyboard shortcut.
+    pub fn tap_chord(&mut self, chord: &Chord) -> Result<(), SimulationError> {
+        self.press_chord(chord)?;
+        self.release_chord(chord)
+    }
+
+    /// The keyboard layout used by [`InputSimulator::type_text`].
+    pub fn layout(&self) -> &KeyboardLayout {
+        &self.layout
+    }
+
+    /// Change the keyboard layout used by [`InputSimulator::type_text`].
+    ///
+    /// This should match the layout that is active on the host.
+    pub fn set_layout(&mut self, layout: KeyboardLayout) {
+        self.layout = layout;
+    }
+
+    /// Replace the keyboard layout with the one currently active on
+    /// the host, if the backend can determine it.
+    ///
+    /// Returns whether the layout was replaced. Simulators created
+    /// without an explicit layout do this once when they are built,
+    /// so this is only needed when the host layout changes later.
+    pub fn refresh_layout(&mut self) -> Result<bool, SimulationError> {
+        let layout = self.backend().keyboard_layout()?;
+        match layout {
+            Some(layout) => {
+                self.layout = layout;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Type a string by pressing the keys that produce each of its
+    /// characters in the simulator's [`KeyboardLayout`].
+    ///
+    /// Characters the layout cannot produce are typed with
+    /// [`InputSimulator::type_unicode`] instead. Where that is not
+    /// possible, nothing is typed and an `UnsupportedCharacters`
+    /// error lists every such character. If typing fails partway,
+    /// the characters before the failing one have already been typed.
+    pub fn type_text(&mut self, text: &str) -> Result<(), SimulationError> {
+        if !self.backend().can_type_unicode() {
+            let unsupported = self.layout.unsupported_chars(text);
+            if !unsupported.is_empty() {
+                return Err(SimulationError::UnsupportedCharacters(unsupported));
+            }
+        }
+        for c in text.chars() {
+            match self.layout.stroke(c) {
+                Some(stroke) => self.tap_stroke(stroke)?,
+                None => self.backend().type_unicode(c)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Type a single character regardless of the keyboard layout.
+    ///
+    /// On Linux, this temporarily binds the character to an unused
+    /// keycode of the X server and presses it on the virtual
+    /// keyboard, so it requires an X11 connection.
+    /// Other platforms return an `UnsupportedCharacters` error.
+    pub fn type_unicode(&mut self, c: char) -> Result<(), SimulationError> {
+        self.backend().type_unicode(c)
+    }
+
+    fn tap_stroke(&mut self, stroke: KeyStroke) -> Result<(), SimulationError> {
+        let modifiers = self.layout.modifier_keys(stroke.modifiers);
+        let chord = Chord::new(modifiers.chain([stroke.key]));
+        self.tap_chord(&chord)
     }
 
     pub fn touch_down(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.0.touch_down(slot, x, y)
+        self.touch_down_with(slot, x, y, TouchAttributes::default())
+    }
+
+    /// Start a touch contact with the given pressure, contact size,
+    /// orientation or tool.
+    ///
+    /// Windows ignores the tool, and macOS does not simulate touch.
+    pub fn touch_down_with(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.backend().touch_down(slot, x, y, attributes)
+    }
+
+    /// Start a touch contact at a point relative to the top left
+    /// corner of a monitor.
+    pub fn touch_down_on(
+        &mut self,
+        monitor: &Monitor,
+        slot: i32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), SimulationError> {
+        let (x, y) = monitor.to_screen(x, y);
+        self.touch_down(slot, x, y)
     }
 
     pub fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
-        self.0.touch_up(slot)
+        self.backend().touch_up(slot)
     }
 
     pub fn touch_move(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.0.touch_move(slot, x, y)
+        self.touch_move_with(slot, x, y, TouchAttributes::default())
     }
 
-    pub fn pen(
+    /// Move a touch contact, changing the attributes that are set.
+    pub fn touch_move_with(
         &mut self,
+        slot: i32,
         x: i32,
         y: i32,
-        pressure: f64,
-        tilt_x: i32,
-        tilt_y: i32,
+        attributes: TouchAttributes,
     ) -> Result<(), SimulationError> {
-        self.0.pen(x, y, pressure, tilt_x, tilt_y)
+        self.backend().touch_move(slot, x, y, attributes)
+    }
+
+    /// Move a touch contact to a point relative to the top left
+    /// corner of a monitor.
+    pub fn touch_move_on(
+        &mut self,
+        monitor: &Monitor,
+        slot: i32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), SimulationError> {
+        let (x, y) = monitor.to_screen(x, y);
+        self.touch_move(slot, x, y)
+    }
+
+    /// Update several touch slots in a single frame, so that the
+    /// contacts move together like on a real touchscreen.
+    ///
+    /// On Linux, all the updates are delivered in one evdev frame.
+    pub fn touch_frame(&mut self, contacts: &[TouchContact]) -> Result<(), SimulationError> {
+        self.backend().touch_frame(contacts)
+    }
+
+    /// Update the pen, moving it in or out of range, pressing it on
+    /// the surface or lifting it, and pressing its barrel buttons.
+    ///
+    /// Switching between the pen and the eraser takes the pen out of
+    /// range first. Windows does not report the hover distance, and
+    /// macOS does not simulate a pen.
+    pub fn pen(&mut self, state: PenState) -> Result<(), SimulationError> {
+        self.backend().pen(state)
+    }
+
+    /// Update the pen, with its position relative to the top left
+    /// corner of a monitor.
+    pub fn pen_on(&mut self, monitor: &Monitor, state: PenState) -> Result<(), SimulationError> {
+        let (x, y) = monitor.to_screen(state.x, state.y);
+        self.pen(PenState { x, y, ..state })
+    }
+
+    /// Perform a batch of operations in order.
+    ///
+    /// Backends may group the operations into larger atomic units.
+    /// On Linux, consecutive operations on the same virtual device
+    /// are delivered as a single evdev frame where that does not
+    /// change their meaning.
+    pub fn send(&mut self, events: &[InputEvent]) -> Result<(), SimulationError> {
+        self.backend().send(events)
     }
 
     /// This function gets the combined size of the virtual "screen space", NOT
@@ -152,6 +548,52 @@ impl InputSimulator {
     ///
     /// This is useful for many calculations involving input simulation.
     pub fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
-        self.0.get_screen_size()
+        self.backend().get_screen_size()
+    }
+
+    /// The monitors that make up the virtual screen.
+    ///
+    /// On Linux, the monitors are the active RandR outputs of the X
+    /// server. Headless simulators, X servers without RandR 1.3, and
+    /// the other platforms report a single monitor covering the whole
+    /// virtual screen.
+    pub fn monitors(&self) -> Result<Vec<Monitor>, SimulationError> {
+        self.backend().monitors()
+    }
+
+    /// The primary monitor, or the first one if none is marked as
+    /// primary.
+    pub fn primary_monitor(&self) -> Result<Option<Monitor>, SimulationError> {
+        let monitors = self.monitors()?;
+        let primary = monitors.iter().position(|monitor| monitor.primary);
+        Ok(monitors.into_iter().nth(primary.unwrap_or(0)))
+    }
+
+    /// Where the pointer currently is on the host, in virtual screen
+    /// coordinates.
+    ///
+    /// Unlike the position the simulator tracks for
+    /// [`InputSimulator::move_mouse_smooth`], this reflects moves made
+    /// by other devices too. Returns `None` if the platform cannot
+    /// report it, such as on headless Linux simulators.
+    pub fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        self.backend().mouse_position()
+    }
+
+    /// The mouse buttons currently held down on the host, by any
+    /// device.
+    ///
+    /// On Linux and macOS, only [`MouseButton::Left`],
+    /// [`MouseButton::Middle`] and [`MouseButton::Right`] can be
+    /// reported. Returns `None` if the platform cannot report the
+    /// buttons, such as on headless Linux simulators.
+    pub fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        self.backend().mouse_buttons()
+    }
+}
+
+impl Drop for InputSimulator {
+    fn drop(&mut self) {
+        let _ = self.release_all();
     }
 }
@@ -1,15 +1,26 @@
-use std::sync::Arc;
-use crate::Key;
+use crate::backend::{dispatch, InputBackend};
+use crate::{
+    DeviceClass, InputSimulatorBuilder, Key, KeyStroke, KeyboardLayout, Modifiers, Monitor,
+    MouseButton, PenState, PenTool, Rotation, TouchAttributes, TouchContact, TouchTool,
+};
 use evdev::{
-    uinput::VirtualDevice, AbsInfo, AbsoluteAxisCode, AttributeSet, EventType, InputEvent, KeyCode,
-    PropType, RelativeAxisCode, UinputAbsSetup,
+    uinput::{VirtualDevice, VirtualDeviceBuilder},
+    AbsInfo, AbsoluteAxisCode, AttributeSet, BusType, EventType, InputEvent, InputId, KeyCode,
+    PropType, RelativeAxisCode, SynchronizationCode, UinputAbsSetup,
 };
 use log::info;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use std::time::Duration;
 use strum::IntoEnumIterator;
 use thiserror::Error;
-use x11rb::protocol::xproto::ConnectionExt;
-use x11rb::protocol::xtest::ConnectionExt as XtestConnectionExt;
-use x11rb::{connection::Connection, rust_connection::RustConnection};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::randr::{self, ConnectionExt as RandrConnectionExt};
+use x11rb::protocol::xinput::{self, ConnectionExt as XinputConnectionExt};
+use x11rb::protocol::xproto::{self, ConnectionExt};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
 
 /// An error returned by the [InputSimulator](crate::InputSimulator).
 #[derive(Error, Debug)]
@@ -22,195 +33,1293 @@ pub enum SimulationError {
     IoError(#[from] std::io::Error),
     #[error("X11 connect error: {0}")]
     X11ConnectError(#[from] x11rb::errors::ConnectError),
+    #[error("This operation requires an X11 connection, but the simulator is headless")]
+    NoX11Connection,
+    #[error("The X server has no unused keycode that can be used to type arbitrary characters")]
+    NoSpareKeycode,
+    #[error("The keyboard layout cannot produce these characters: {0:?}")]
+    UnsupportedCharacters(Vec<char>),
+    #[error("The {0:?} device class was not enabled when the simulator was built")]
+    DeviceDisabled(DeviceClass),
+    #[error("The X server has no active output named {0:?}")]
+    UnknownOutput(String),
+    #[error("The touchscreen has no slot {0}")]
+    InvalidSlot(i32),
+    #[error("Failed to install the signal handler: {0}")]
+    SignalHandler(String),
+    #[error("Backend error: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl SimulationError {
+    /// Whether the operation was refused before anything reached the
+    /// host, so that it cannot have partially taken effect.
+    pub(crate) fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            SimulationError::NoX11Connection
+                | SimulationError::UnsupportedCharacters(_)
+                | SimulationError::DeviceDisabled(_)
+                | SimulationError::UnknownOutput(_)
+                | SimulationError::InvalidSlot(_)
+        )
+    }
+}
+
+/// One of the uinput devices created by the simulator on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UinputDevice {
+    Keyboard,
+    /// Relative motion and the mouse wheel.
+    RelativeMouse,
+    /// Absolute motion and mouse buttons.
+    AbsoluteMouse,
+    Touchscreen,
+    Pen,
+}
+
+/// The identity a uinput device presents to the rest of the system.
+///
+/// udev rules, libinput quirks and hwdb entries match on these
+/// values, so setting them lets deployments target the simulated
+/// devices individually.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceIdentity {
+    /// The device name, as shown by `libinput list-devices`.
+    pub name: String,
+    /// One of the `BUS_*` constants from `linux/input.h`, for
+    /// example `0x03` for USB.
+    pub bus_type: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+    /// The physical path of the device, e.g. `usb-0000:00:14.0-1/input0`.
+    pub phys: Option<String>,
+}
+
+impl DeviceIdentity {
+    const BUS_USB: u16 = 0x03;
+
+    /// An identity with the given name and the same IDs as the
+    /// default devices.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            bus_type: Self::BUS_USB,
+            vendor: 0x1234,
+            product: 0x5678,
+            version: 0x111,
+            phys: None,
+        }
+    }
+
+    /// Set the physical path of the device.
+    pub fn with_phys(mut self, phys: impl Into<String>) -> Self {
+        self.phys = Some(phys.into());
+        self
+    }
+
+    /// A plain USB keyboard.
+    pub fn generic_usb_keyboard() -> Self {
+        Self {
+            name: "Generic USB Keyboard".into(),
+            bus_type: Self::BUS_USB,
+            vendor: 0x04d9,
+            product: 0x1603,
+            version: 0x0110,
+            phys: Some("usb-0000:00:14.0-1/input0".into()),
+        }
+    }
+
+    /// A plain USB optical mouse.
+    pub fn generic_usb_mouse() -> Self {
+        Self {
+            name: "Generic USB Optical Mouse".into(),
+            bus_type: Self::BUS_USB,
+            vendor: 0x093a,
+            product: 0x2510,
+            version: 0x0111,
+            phys: Some("usb-0000:00:14.0-2/input0".into()),
+        }
+    }
+
+    /// A USB HID touchscreen panel.
+    pub fn generic_usb_touchscreen() -> Self {
+        Self {
+            name: "Generic USB Touchscreen".into(),
+            bus_type: Self::BUS_USB,
+            vendor: 0x0eef,
+            product: 0x0001,
+            version: 0x0100,
+            phys: Some("usb-0000:00:14.0-3/input0".into()),
+        }
+    }
+
+    /// A pen tablet that identifies itself with Wacom's vendor ID,
+    /// so that tablet-specific configuration applies to it.
+    pub fn wacom_like_tablet() -> Self {
+        Self {
+            name: "Wacom Intuos Pro M Pen".into(),
+            bus_type: Self::BUS_USB,
+            vendor: 0x056a,
+            product: 0x0357,
+            version: 0x0110,
+            phys: Some("usb-0000:00:14.0-4/input0".into()),
+        }
+    }
+
+    /// The identity used when none is configured.
+    pub(crate) fn default_for(device: UinputDevice) -> Self {
+        Self::new(match device {
+            UinputDevice::Keyboard => "Simulated input-device Keyboard",
+            UinputDevice::RelativeMouse => "Simulated input-device Relative Mouse",
+            UinputDevice::AbsoluteMouse => "Simulated input-device Absolute Mouse",
+            UinputDevice::Touchscreen => "Simulated input-device Touchscreen",
+            UinputDevice::Pen => "Simulated input-device Pen",
+        })
+    }
+}
+
+/// The axes of the uinput touchscreen.
+///
+/// Positions range from zero to the maximum on each axis, and are
+/// mapped onto the screen or the output the touchscreen is
+/// restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TouchscreenGeometry {
+    pub max_x: i32,
+    pub max_y: i32,
+    /// The number of position units per millimetre along X, or zero
+    /// if the physical size is unknown.
+    pub resolution_x: i32,
+    /// The number of position units per millimetre along Y, or zero
+    /// if the physical size is unknown.
+    pub resolution_y: i32,
+    /// The number of contacts that can touch the screen at once.
+    pub slots: i32,
+    /// The largest pressure value.
+    pub max_pressure: i32,
+}
+
+impl Default for TouchscreenGeometry {
+    fn default() -> Self {
+        Self {
+            max_x: 100_000,
+            max_y: 100_000,
+            resolution_x: 0,
+            resolution_y: 0,
+            slots: 10,
+            max_pressure: 100_000,
+        }
+    }
+}
+
+impl TouchscreenGeometry {
+    /// Set the resolution so that the position axes span a panel of
+    /// the given size in millimetres.
+    pub fn with_physical_size(mut self, width_mm: i32, height_mm: i32) -> Self {
+        self.resolution_x = self.max_x / width_mm.max(1);
+        self.resolution_y = self.max_y / height_mm.max(1);
+        self
+    }
+
+    pub fn with_slots(mut self, slots: i32) -> Self {
+        self.slots = slots;
+        self
+    }
+}
+
+/// The axes of the uinput pen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenGeometry {
+    pub max_x: i32,
+    pub max_y: i32,
+    /// The number of position units per millimetre along X, or zero
+    /// if the physical size is unknown.
+    pub resolution_x: i32,
+    /// The number of position units per millimetre along Y, or zero
+    /// if the physical size is unknown.
+    pub resolution_y: i32,
+    /// The largest pressure value. Real pens typically report 1024,
+    /// 2048 or 8192 levels.
+    pub max_pressure: i32,
+    /// The largest tilt in either direction, in degrees.
+    pub max_tilt: i32,
+}
+
+impl Default for PenGeometry {
+    fn default() -> Self {
+        Self {
+            max_x: 100_000,
+            max_y: 100_000,
+            resolution_x: 10,
+            resolution_y: 10,
+            max_pressure: 100_000,
+            max_tilt: 90,
+        }
+    }
+}
+
+impl PenGeometry {
+    /// Set the resolution so that the position axes span an active
+    /// area of the given size in millimetres.
+    pub fn with_physical_size(mut self, width_mm: i32, height_mm: i32) -> Self {
+        self.resolution_x = self.max_x / width_mm.max(1);
+        self.resolution_y = self.max_y / height_mm.max(1);
+        self
+    }
+}
+
+/// How the uinput pen presents itself.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PenMode {
+    /// A pen display, on which the pen draws directly on the screen.
+    /// The whole device range covers the screen, or the output the
+    /// pen is restricted to.
+    #[default]
+    Display,
+    /// An opaque tablet that is not part of a screen, whose active
+    /// area is mapped to the screen or output.
+    Opaque(TabletMapping),
+}
+
+/// How the active area of an opaque tablet is mapped to the screen.
+///
+/// Tablet drivers offer the same settings. They should match the
+/// driver's configuration, so that the pen lands where requested.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabletMapping {
+    /// The part of the tablet that is mapped to the screen, as the
+    /// fractions of its width and height at the left, top, right and
+    /// bottom edges.
+    pub area: (f64, f64, f64, f64),
+    /// Crop the right or bottom of the area so that it has the same
+    /// aspect ratio as the screen, using the physical size of the
+    /// tablet if its resolution is known.
+    pub keep_aspect_ratio: bool,
+}
+
+impl Default for TabletMapping {
+    fn default() -> Self {
+        Self {
+            area: (0.0, 0.0, 1.0, 1.0),
+            keep_aspect_ratio: false,
+        }
+    }
+}
+
+impl TabletMapping {
+    pub fn with_area(mut self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        self.area = (left, top, right, bottom);
+        self
+    }
+
+    pub fn with_keep_aspect_ratio(mut self, keep_aspect_ratio: bool) -> Self {
+        self.keep_aspect_ratio = keep_aspect_ratio;
+        self
+    }
+
+    /// The rectangle of the tablet, in device units, that covers a
+    /// screen area of the given size.
+    fn window(&self, geometry: &PenGeometry, (width, height): (i32, i32)) -> [f64; 4] {
+        let (left, top, right, bottom) = self.area;
+        let (max_x, max_y) = (geometry.max_x as f64, geometry.max_y as f64);
+        let [x0, y0] = [left * max_x, top * max_y];
+        let [mut x1, mut y1] = [right * max_x, bottom * max_y];
+        if self.keep_aspect_ratio && width > 0 && height > 0 {
+            let per_mm = |resolution: i32| match resolution {
+                0 => 1.0,
+                resolution => resolution as f64,
+            };
+            let (res_x, res_y) = (per_mm(geometry.resolution_x), per_mm(geometry.resolution_y));
+            let area_ratio = ((x1 - x0) / res_x) / ((y1 - y0) / res_y);
+            let screen_ratio = width as f64 / height as f64;
+            if area_ratio > screen_ratio {
+                x1 = x0 + (y1 - y0) / res_y * screen_ratio * res_x;
+            } else {
+                y1 = y0 + (x1 - x0) / res_x / screen_ratio * res_y;
+            }
+        }
+        [x0, y0, x1, y1]
+    }
 }
 
 pub(crate) struct PlatformImpl {
-    conn: Arc<RustConnection>,
-    rel_mouse_device: VirtualDevice,
-    keyboard_device: VirtualDevice,
-    touch_device: VirtualDevice,
-    pen_device: VirtualDevice,
+    /// The X11 connection, unless running headless.
+    conn: Option<Arc<RustConnection>>,
+    /// The layout of the screen, kept up to date by the event thread
+    /// unless running headless.
+    geometry: Arc<RwLock<ScreenGeometry>>,
+    /// The outputs that devices are restricted to.
+    outputs: HashMap<UinputDevice, String>,
+    rel_mouse_device: Option<VirtualDevice>,
+    abs_mouse_device: Option<VirtualDevice>,
+    keyboard_device: Option<VirtualDevice>,
+    touch_device: Option<VirtualDevice>,
+    pen_device: Option<VirtualDevice>,
+    touch_geometry: TouchscreenGeometry,
+    pen_geometry: PenGeometry,
+    pen_mode: PenMode,
+    touches: TouchState,
     wheel_x: i32,
     wheel_y: i32,
-    last_pressure: f64,
+    /// The last state of the pen while it was in range.
+    pen: Option<PenState>,
+    /// An unused X keycode, borrowed to type arbitrary characters.
+    spare_keycode: Option<u8>,
 }
 
 impl PlatformImpl {
     /// Create a new input simulator.
-    pub(crate) fn new() -> Result<Self, SimulationError> {
-        let mut keyboard_device = VirtualDevice::builder()?
-            .name("Simulated input-device Keyboard")
-            .with_keys(&AttributeSet::from_iter(
-                Key::iter().map(|k| KeyCode::from(k)),
-            ))?
-            .build()?;
-
-        for path in keyboard_device.enumerate_dev_nodes_blocking()? {
-            let path = path?;
-            info!("Keyboard device available as {}", path.display());
-        }
-
-        let mut rel_mouse_device = VirtualDevice::builder()?
-            .name("Simulated input-device Relative Mouse")
-            .with_keys(&AttributeSet::from_iter([
-                KeyCode::BTN_LEFT,
-                KeyCode::BTN_MIDDLE,
-                KeyCode::BTN_RIGHT,
-            ]))?
-            .with_relative_axes(&AttributeSet::from_iter([
-                RelativeAxisCode::REL_X,
-                RelativeAxisCode::REL_Y,
-                RelativeAxisCode::REL_WHEEL,
-                RelativeAxisCode::REL_HWHEEL,
-                RelativeAxisCode::REL_WHEEL_HI_RES,
-                RelativeAxisCode::REL_HWHEEL_HI_RES,
-            ]))?
-            .build()?;
-
-        for path in rel_mouse_device.enumerate_dev_nodes_blocking()? {
-            let path = path?;
-            info!("Relative mouse device available as {}", path.display());
-        }
-
-        let mut touch_device = VirtualDevice::builder()?
-            .name("Simulated input-device Touchscreen")
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_MT_SLOT,
-                AbsInfo::new(0, 0, 9, 0, 0, 0),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_MT_POSITION_X,
-                AbsInfo::new(0, 0, 100_000, 0, 0, 0),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_MT_POSITION_Y,
-                AbsInfo::new(0, 0, 100_000, 0, 0, 0),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_MT_TRACKING_ID,
-                AbsInfo::new(0, 0, 65535, 0, 0, 0),
-            ))?
-            .with_properties(&AttributeSet::from_iter([PropType::DIRECT]))?
-            .build()?;
-
-        for path in touch_device.enumerate_dev_nodes_blocking()? {
-            let path = path?;
-            info!("Touchscreen device available as {}", path.display());
-        }
-
-        let mut pen_device = VirtualDevice::builder()?
-            .name("Simulated input-device Pen")
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_X,
-                AbsInfo::new(0, 0, 100_000, 0, 0, 10),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_Y,
-                AbsInfo::new(0, 0, 100_000, 0, 0, 10),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_PRESSURE,
-                AbsInfo::new(0, 0, 100_000, 0, 0, 0),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_TILT_X,
-                AbsInfo::new(0, -90, 90, 0, 0, 0),
-            ))?
-            .with_absolute_axis(&UinputAbsSetup::new(
-                AbsoluteAxisCode::ABS_TILT_Y,
-                AbsInfo::new(0, -90, 90, 0, 0, 0),
-            ))?
-            .with_keys(&AttributeSet::from_iter([
-                KeyCode::BTN_TOUCH,
-                KeyCode::BTN_TOOL_PEN,
-                KeyCode::BTN_STYLUS,
-            ]))?
-            .with_properties(&AttributeSet::from_iter([PropType::DIRECT]))?
-            .with_properties(&AttributeSet::from_iter([PropType::POINTER]))?
-            .build()?;
-
-        for path in pen_device.enumerate_dev_nodes_blocking()? {
-            let path = path?;
-            info!("Pen device available as {}", path.display());
-        }
-
-        let (conn, _screen_num) = x11rb::connect(None)?;
-        let conn = Arc::new(conn);
-        let event_conn = conn.clone();
-        std::thread::spawn(move || {
-            loop {
-                match event_conn.wait_for_event() {
-                    Ok(_) => {}
-                    Err(_) => break,
+    ///
+    /// If the builder supplies a screen size, no X11 connection is
+    /// made and only the uinput devices are created.
+    pub(crate) fn new(builder: &InputSimulatorBuilder) -> Result<Self, SimulationError> {
+        let keyboard_device = builder
+            .has_device(DeviceClass::Keyboard)
+            .then(|| create_keyboard(&builder.identity(UinputDevice::Keyboard)))
+            .transpose()?;
+        let rel_mouse_device = builder
+            .has_device(DeviceClass::Mouse)
+            .then(|| create_rel_mouse(&builder.identity(UinputDevice::RelativeMouse)))
+            .transpose()?;
+        let abs_mouse_device = builder
+            .has_device(DeviceClass::Mouse)
+            .then(|| create_abs_mouse(&builder.identity(UinputDevice::AbsoluteMouse)))
+            .transpose()?;
+        let touch_device = builder
+            .has_device(DeviceClass::Touch)
+            .then(|| {
+                create_touch(
+                    &builder.identity(UinputDevice::Touchscreen),
+                    &builder.touch_geometry,
+                )
+            })
+            .transpose()?;
+        let pen_device = builder
+            .has_device(DeviceClass::Pen)
+            .then(|| {
+                create_pen(
+                    &builder.identity(UinputDevice::Pen),
+                    &builder.pen_geometry,
+                    builder.pen_mode,
+                )
+            })
+            .transpose()?;
+
+        let outputs: HashMap<_, _> = [UinputDevice::Touchscreen, UinputDevice::Pen]
+            .into_iter()
+            .filter(|&device| builder.has_device(device.class()))
+            .filter_map(|device| Some((device, builder.outputs.get(&device)?.clone())))
+            .collect();
+
+        let (conn, geometry) = match builder.screen_size {
+            Some(size) => {
+                if !outputs.is_empty() {
+                    return Err(SimulationError::NoX11Connection);
                 }
+                let geometry = ScreenGeometry {
+                    size,
+                    monitors: vec![Monitor::whole_screen(size)],
+                };
+                (None, Arc::new(RwLock::new(geometry)))
             }
-        });
+            None => {
+                let (conn, _screen_num) = x11rb::connect(None)?;
+                let conn = Arc::new(conn);
+                let randr = has_randr(&conn)?;
+                if randr {
+                    // Select the notifications before the first query,
+                    // so that no change can be missed.
+                    let root_window = conn.setup().roots[0].root;
+                    conn.randr_select_input(
+                        root_window,
+                        randr::NotifyMask::SCREEN_CHANGE
+                            | randr::NotifyMask::CRTC_CHANGE
+                            | randr::NotifyMask::OUTPUT_CHANGE,
+                    )?
+                    .check()?;
+                }
+                let geometry = ScreenGeometry::query(&conn, randr)?;
+                let mappings: Vec<_> = outputs
+                    .iter()
+                    .map(|(&device, output)| (builder.identity(device).name, output.clone()))
+                    .collect();
+                for (name, output) in &mappings {
+                    let monitor = geometry
+                        .monitor(output)
+                        .ok_or_else(|| SimulationError::UnknownOutput(output.clone()))?;
+                    map_to_output(&conn, name, monitor, geometry.size)?;
+                }
+
+                let geometry = Arc::new(RwLock::new(geometry));
+                // Without RandR, the screen cannot change, so there is
+                // nothing to watch.
+                if randr {
+                    let event_conn = conn.clone();
+                    let event_geometry = geometry.clone();
+                    std::thread::spawn(move || {
+                        watch_screen(&event_conn, &event_geometry, &mappings)
+                    });
+                }
+                (Some(conn), geometry)
+            }
+        };
 
         Ok(Self {
             wheel_x: 0,
             wheel_y: 0,
-            last_pressure: 0.0,
+            touches: TouchState::default(),
+            pen: None,
+            spare_keycode: None,
             rel_mouse_device,
+            abs_mouse_device,
             keyboard_device,
             touch_device,
             pen_device,
+            touch_geometry: builder.touch_geometry,
+            pen_geometry: builder.pen_geometry,
+            pen_mode: builder.pen_mode,
             conn,
+            geometry,
+            outputs,
         })
     }
+}
 
-    pub(crate) fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
-        let root_window = self.conn.setup().roots[0].root;
-        self.conn
-            .warp_pointer(x11rb::NONE, root_window, 0, 0, 0, 0, x as i16, y as i16)?;
-        self.conn.flush()?;
-        Ok(())
+/// The size of the screen and the monitors on it.
+#[derive(Debug, Clone)]
+struct ScreenGeometry {
+    size: (i32, i32),
+    monitors: Vec<Monitor>,
+}
+
+impl ScreenGeometry {
+    /// Query the geometry of the screen. Without RandR, the whole
+    /// screen is reported as a single monitor.
+    fn query(conn: &RustConnection, randr: bool) -> Result<Self, SimulationError> {
+        let size = root_size(conn)?;
+        let mut monitors = if randr {
+            query_monitors(conn)?
+        } else {
+            Vec::new()
+        };
+        if monitors.is_empty() {
+            monitors.push(Monitor::whole_screen(size));
+        }
+        Ok(Self { size, monitors })
     }
 
-    pub(crate) fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
-        self.rel_mouse_device.emit(&[
-            InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, x),
-            InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_Y.0, y),
-        ])?;
-        Ok(())
+    fn monitor(&self, output: &str) -> Option<&Monitor> {
+        self.monitors.iter().find(|monitor| monitor.name == output)
     }
+}
 
-    pub(crate) fn left_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(4, 1, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
-        Ok(())
+/// Drain the events of the X11 connection, refreshing the cached
+/// geometry and remapping the devices restricted to an output
+/// whenever RandR reports a change.
+fn watch_screen(
+    conn: &RustConnection,
+    geometry: &RwLock<ScreenGeometry>,
+    mappings: &[(String, String)],
+) {
+    while let Ok(event) = conn.wait_for_event() {
+        if !matches!(
+            event,
+            Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_)
+        ) {
+            continue;
+        }
+        // A single change is reported through several events, so
+        // handle everything that is already queued at once.
+        while let Ok(Some(_)) = conn.poll_for_event() {}
+        let updated = match ScreenGeometry::query(conn, true) {
+            Ok(updated) => updated,
+            Err(err) => {
+                log::warn!("Failed to query the screen geometry: {err}");
+                continue;
+            }
+        };
+        for (name, output) in mappings {
+            let Some(monitor) = updated.monitor(output) else {
+                log::warn!("{output} is no longer active, so {name} was not remapped");
+                continue;
+            };
+            if let Err(err) = map_to_output(conn, name, monitor, updated.size) {
+                log::warn!("Failed to map {name} to {output}: {err}");
+            }
+        }
+        *geometry.write().unwrap_or_else(PoisonError::into_inner) = updated;
     }
+}
 
-    pub(crate) fn middle_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(4, 2, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
-        Ok(())
+/// The oldest RandR version that can list the monitors and report
+/// changes to them.
+const RANDR_VERSION: (u32, u32) = (1, 3);
+
+/// Whether the X server supports a recent enough version of RandR.
+fn has_randr(conn: &RustConnection) -> Result<bool, SimulationError> {
+    if conn
+        .extension_information(randr::X11_EXTENSION_NAME)?
+        .is_none()
+    {
+        return Ok(false);
     }
+    let (major, minor) = RANDR_VERSION;
+    let version = conn.randr_query_version(major, minor)?.reply()?;
+    Ok((version.major_version, version.minor_version) >= RANDR_VERSION)
+}
 
-    pub(crate) fn right_mouse_down(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(4, 3, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
-        Ok(())
+/// The size of the root window, which spans every monitor.
+fn root_size(conn: &RustConnection) -> Result<(i32, i32), SimulationError> {
+    let root_window = conn.setup().roots[0].root;
+    let geometry = conn.get_geometry(root_window)?.reply()?;
+    Ok((geometry.width as _, geometry.height as _))
+}
+
+/// The active RandR outputs, as monitors.
+fn query_monitors(conn: &RustConnection) -> Result<Vec<Monitor>, SimulationError> {
+    let root_window = conn.setup().roots[0].root;
+    let resources = conn
+        .randr_get_screen_resources_current(root_window)?
+        .reply()?;
+    let primary = conn.randr_get_output_primary(root_window)?.reply()?.output;
+    let mut monitors = Vec::new();
+    for &output in &resources.outputs {
+        let info = conn
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+        if info.connection != randr::Connection::CONNECTED || info.crtc == x11rb::NONE {
+            continue;
+        }
+        let crtc = conn
+            .randr_get_crtc_info(info.crtc, resources.config_timestamp)?
+            .reply()?;
+        let rotation = if crtc.rotation.contains(randr::Rotation::ROTATE90) {
+            Rotation::Left
+        } else if crtc.rotation.contains(randr::Rotation::ROTATE180) {
+            Rotation::Inverted
+        } else if crtc.rotation.contains(randr::Rotation::ROTATE270) {
+            Rotation::Right
+        } else {
+            Rotation::Normal
+        };
+        monitors.push(Monitor {
+            name: String::from_utf8_lossy(&info.name).into_owned(),
+            x: crtc.x.into(),
+            y: crtc.y.into(),
+            width: crtc.width.into(),
+            height: crtc.height.into(),
+            rotation,
+            primary: output == primary,
+        });
     }
+    Ok(monitors)
+}
 
-    pub(crate) fn left_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(5, 1, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
-        Ok(())
+/// How long to wait for the X server to pick up a new uinput device.
+const X_DEVICE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Map every X input device created for the uinput device `name` to
+/// a monitor, by setting its coordinate transformation matrix.
+///
+/// Drivers may split a device into several X devices, such as a pen
+/// and an eraser, whose names start with the name of the uinput
+/// device.
+fn map_to_output(
+    conn: &RustConnection,
+    name: &str,
+    monitor: &Monitor,
+    (width, height): (i32, i32),
+) -> Result<(), SimulationError> {
+    conn.xinput_xi_query_version(2, 0)?.reply()?;
+    let property = conn
+        .intern_atom(false, b"Coordinate Transformation Matrix")?
+        .reply()?
+        .atom;
+    let float = conn.intern_atom(false, b"FLOAT")?.reply()?.atom;
+
+    let (width, height) = (width as f32, height as f32);
+    #[rustfmt::skip]
+    let matrix = [
+        monitor.width as f32 / width, 0.0, monitor.x as f32 / width,
+        0.0, monitor.height as f32 / height, monitor.y as f32 / height,
+        0.0, 0.0, 1.0,
+    ];
+    let matrix = xinput::XIChangePropertyAux::Data32(matrix.map(f32::to_bits).to_vec());
+
+    let deadline = std::time::Instant::now() + X_DEVICE_TIMEOUT;
+    loop {
+        let devices = conn.xinput_xi_query_device(xinput::Device::ALL)?.reply()?;
+        let ids: Vec<_> = devices
+            .infos
+            .iter()
+            .filter(|info| {
+                let device_name = String::from_utf8_lossy(&info.name);
+                device_name == name || device_name.starts_with(&format!("{name} "))
+            })
+            .map(|info| info.deviceid)
+            .collect();
+        if !ids.is_empty() {
+            for id in ids {
+                conn.xinput_xi_change_property(
+                    id,
+                    xproto::PropMode::REPLACE,
+                    property,
+                    float,
+                    9,
+                    &matrix,
+                )?
+                .check()?;
+            }
+            return Ok(());
+        }
+        if std::time::Instant::now() >= deadline {
+            log::warn!(
+                "{name} did not appear as an X input device, so it was not mapped to {}",
+                monitor.name
+            );
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn device_builder(identity: &DeviceIdentity) -> Result<VirtualDeviceBuilder<'_>, SimulationError> {
+    // uinput names are limited to 80 bytes, and evdev asserts that
+    // the name and its terminator take up less than that.
+    if identity.name.len() >= 79 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "uinput device names must be at most 78 bytes long",
+        )
+        .into());
+    }
+    let mut builder = VirtualDevice::builder()?
+        .name(&identity.name)
+        .input_id(InputId::new(
+            BusType(identity.bus_type),
+            identity.vendor,
+            identity.product,
+            identity.version,
+        ));
+    if let Some(phys) = &identity.phys {
+        let phys = CString::new(phys.as_str())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+        builder = builder.with_phys(&phys)?;
     }
+    Ok(builder)
+}
 
-    pub(crate) fn middle_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(5, 2, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
-        Ok(())
+fn log_dev_nodes(device: &mut VirtualDevice, label: &str) -> Result<(), SimulationError> {
+    for path in device.enumerate_dev_nodes_blocking()? {
+        let path = path?;
+        info!("{} available as {}", label, path.display());
+    }
+    Ok(())
+}
+
+/// Every evdev key code, excluding the ranges reserved for buttons,
+/// so that [Key::Raw] can send any key.
+///
+/// The D-pad buttons sit among the keys, but udev counts them as
+/// joystick buttons, so they are left out as well.
+fn all_key_codes() -> impl Iterator<Item = KeyCode> {
+    let dpad = KeyCode::BTN_DPAD_UP.code()..=KeyCode::BTN_DPAD_RIGHT.code();
+    (KeyCode::KEY_ESC.code()..KeyCode::BTN_0.code())
+        .chain(KeyCode::KEY_OK.code()..KeyCode::BTN_TRIGGER_HAPPY1.code())
+        .filter(move |code| !dpad.contains(code))
+        .map(KeyCode::new)
+}
+
+fn create_keyboard(identity: &DeviceIdentity) -> Result<VirtualDevice, SimulationError> {
+    let mut device = device_builder(identity)?
+        .with_keys(&AttributeSet::from_iter(all_key_codes()))?
+        .build()?;
+    log_dev_nodes(&mut device, &identity.name)?;
+    Ok(device)
+}
+
+/// The buttons of the virtual mice: the generic buttons `BTN_0` to
+/// `BTN_9` and the whole `BTN_MOUSE` range, so that
+/// [MouseButton::Raw] can send buttons that have no variant.
+fn mouse_button_codes() -> impl Iterator<Item = KeyCode> {
+    (KeyCode::BTN_0.code()..=KeyCode::BTN_9.code())
+        // BTN_MOUSE is BTN_LEFT, and the range ends at BTN_JOYSTICK,
+        // which is BTN_TRIGGER.
+        .chain(KeyCode::BTN_LEFT.code()..KeyCode::BTN_TRIGGER.code())
+        .map(KeyCode::new)
+}
+
+fn create_rel_mouse(identity: &DeviceIdentity) -> Result<VirtualDevice, SimulationError> {
+    let mut device = device_builder(identity)?
+        .with_keys(&AttributeSet::from_iter(mouse_button_codes()))?
+        .with_relative_axes(&AttributeSet::from_iter([
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+        ]))?
+        .build()?;
+    log_dev_nodes(&mut device, &identity.name)?;
+    Ok(device)
+}
+
+fn create_abs_mouse(identity: &DeviceIdentity) -> Result<VirtualDevice, SimulationError> {
+    let mut device = device_builder(identity)?
+        .with_keys(&AttributeSet::from_iter(mouse_button_codes()))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_X,
+            AbsInfo::new(0, 0, 100_000, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_Y,
+            AbsInfo::new(0, 0, 100_000, 0, 0, 0),
+        ))?
+        .build()?;
+    log_dev_nodes(&mut device, &identity.name)?;
+    Ok(device)
+}
+
+fn create_touch(
+    identity: &DeviceIdentity,
+    geometry: &TouchscreenGeometry,
+) -> Result<VirtualDevice, SimulationError> {
+    if geometry.slots < 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "touchscreens must have at least one slot",
+        )
+        .into());
+    }
+    let x = AbsInfo::new(0, 0, geometry.max_x, 0, 0, geometry.resolution_x);
+    let y = AbsInfo::new(0, 0, geometry.max_y, 0, 0, geometry.resolution_y);
+    let mut device = device_builder(identity)?
+        .with_keys(&AttributeSet::from_iter(
+            [KeyCode::BTN_TOUCH].into_iter().chain(FINGER_TOOLS),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_X, x))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_Y, y))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_SLOT,
+            AbsInfo::new(0, 0, geometry.slots - 1, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_MT_POSITION_X, x))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_MT_POSITION_Y, y))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_TRACKING_ID,
+            AbsInfo::new(0, 0, MAX_TRACKING_ID, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_PRESSURE,
+            AbsInfo::new(0, 0, geometry.max_pressure, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR,
+            AbsInfo::new(0, 0, geometry.max_x, 0, 0, geometry.resolution_x),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_TOUCH_MINOR,
+            AbsInfo::new(0, 0, geometry.max_x, 0, 0, geometry.resolution_x),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_ORIENTATION,
+            AbsInfo::new(0, -90, 90, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_MT_TOOL_TYPE,
+            AbsInfo::new(0, 0, MT_TOOL_PALM, 0, 0, 0),
+        ))?
+        .with_properties(&AttributeSet::from_iter([PropType::DIRECT]))?
+        .build()?;
+    log_dev_nodes(&mut device, &identity.name)?;
+    Ok(device)
+}
+
+fn create_pen(
+    identity: &DeviceIdentity,
+    geometry: &PenGeometry,
+    mode: PenMode,
+) -> Result<VirtualDevice, SimulationError> {
+    let property = match mode {
+        PenMode::Display => PropType::DIRECT,
+        PenMode::Opaque(_) => PropType::POINTER,
+    };
+    let tilt = AbsInfo::new(0, -geometry.max_tilt, geometry.max_tilt, 0, 0, 0);
+    let mut device = device_builder(identity)?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_X,
+            AbsInfo::new(0, 0, geometry.max_x, 0, 0, geometry.resolution_x),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_Y,
+            AbsInfo::new(0, 0, geometry.max_y, 0, 0, geometry.resolution_y),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_PRESSURE,
+            AbsInfo::new(0, 0, geometry.max_pressure, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_TILT_X, tilt))?
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisCode::ABS_TILT_Y, tilt))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_DISTANCE,
+            AbsInfo::new(0, 0, 100_000, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_WHEEL,
+            AbsInfo::new(0, 0, 359, 0, 0, 0),
+        ))?
+        .with_keys(&AttributeSet::from_iter([
+            KeyCode::BTN_TOUCH,
+            KeyCode::BTN_TOOL_PEN,
+            KeyCode::BTN_TOOL_RUBBER,
+            KeyCode::BTN_STYLUS,
+            KeyCode::BTN_STYLUS2,
+        ]))?
+        .with_properties(&AttributeSet::from_iter([property]))?
+        .build()?;
+    log_dev_nodes(&mut device, &identity.name)?;
+    Ok(device)
+}
+
+/// The largest tracking ID of a touch contact. IDs wrap around to
+/// zero after it.
+const MAX_TRACKING_ID: i32 = 65535;
+
+/// The values of ABS_MT_TOOL_TYPE.
+const MT_TOOL_FINGER: i32 = 0;
+const MT_TOOL_PEN: i32 = 1;
+const MT_TOOL_PALM: i32 = 2;
+
+/// The keys that report how many contacts touch the screen, starting
+/// with one contact. The last one stands for five or more.
+const FINGER_TOOLS: [KeyCode; 5] = [
+    KeyCode::BTN_TOOL_FINGER,
+    KeyCode::BTN_TOOL_DOUBLETAP,
+    KeyCode::BTN_TOOL_TRIPLETAP,
+    KeyCode::BTN_TOOL_QUADTAP,
+    KeyCode::BTN_TOOL_QUINTTAP,
+];
+
+/// A contact touching the uinput touchscreen.
+#[derive(Clone)]
+struct ActiveTouch {
+    slot: i32,
+    /// The position in device units.
+    position: (i32, i32),
+}
+
+/// The contacts on the uinput touchscreen, and the state derived from
+/// them that was last reported to the kernel.
+#[derive(Clone, Default)]
+struct TouchState {
+    /// The active contacts, in the order in which they touched down.
+    active: Vec<ActiveTouch>,
+    next_tracking_id: i32,
+    /// The number of contacts last reported through the finger tools.
+    reported_count: usize,
+    /// The single-touch position last reported.
+    reported_position: Option<(i32, i32)>,
+}
+
+impl TouchState {
+    /// Start a contact in `slot`, replacing any contact already in it,
+    /// and return its new tracking ID.
+    fn down(&mut self, slot: i32, position: (i32, i32)) -> i32 {
+        self.up(slot);
+        let tracking_id = self.next_tracking_id;
+        self.next_tracking_id = (tracking_id + 1) % (MAX_TRACKING_ID + 1);
+        self.active.push(ActiveTouch { slot, position });
+        tracking_id
+    }
+
+    fn up(&mut self, slot: i32) {
+        self.active.retain(|touch| touch.slot != slot);
+    }
+
+    fn moved(&mut self, slot: i32, position: (i32, i32)) {
+        if let Some(touch) = self.active.iter_mut().find(|touch| touch.slot == slot) {
+            touch.position = position;
+        }
     }
 
-    pub(crate) fn right_mouse_up(&mut self) -> Result<(), SimulationError> {
-        self.conn.xtest_fake_input(5, 3, 0, x11rb::NONE, 0, 0, 0)?;
-        self.conn.flush()?;
+    /// The events that end a frame: BTN_TOUCH and the finger tool for
+    /// the number of contacts, and the legacy single-touch position,
+    /// which follows the oldest contact. Only changes are reported.
+    fn frame_tail(&mut self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let tool = |count: usize| {
+            count
+                .checked_sub(1)
+                .map(|i| FINGER_TOOLS[i.min(FINGER_TOOLS.len() - 1)])
+        };
+        let count = self.active.len();
+        let (old_tool, new_tool) = (tool(self.reported_count), tool(count));
+        if old_tool != new_tool {
+            if let Some(old_tool) = old_tool {
+                events.push(InputEvent::new(EventType::KEY.0, old_tool.0, 0));
+            }
+            if old_tool.is_none() || new_tool.is_none() {
+                events.push(InputEvent::new(
+                    EventType::KEY.0,
+                    KeyCode::BTN_TOUCH.0,
+                    new_tool.is_some() as i32,
+                ));
+            }
+            if let Some(new_tool) = new_tool {
+                events.push(InputEvent::new(EventType::KEY.0, new_tool.0, 1));
+            }
+        }
+        self.reported_count = count;
+        if let Some(oldest) = self.active.first() {
+            let (x, y) = oldest.position;
+            let (last_x, last_y) = self.reported_position.unzip();
+            if last_x != Some(x) {
+                events.push(InputEvent::new(
+                    EventType::ABSOLUTE.0,
+                    AbsoluteAxisCode::ABS_X.0,
+                    x,
+                ));
+            }
+            if last_y != Some(y) {
+                events.push(InputEvent::new(
+                    EventType::ABSOLUTE.0,
+                    AbsoluteAxisCode::ABS_Y.0,
+                    y,
+                ));
+            }
+            self.reported_position = Some((x, y));
+        }
+        events
+    }
+}
+
+impl UinputDevice {
+    fn class(self) -> DeviceClass {
+        match self {
+            UinputDevice::Keyboard => DeviceClass::Keyboard,
+            UinputDevice::RelativeMouse | UinputDevice::AbsoluteMouse => DeviceClass::Mouse,
+            UinputDevice::Touchscreen => DeviceClass::Touch,
+            UinputDevice::Pen => DeviceClass::Pen,
+        }
+    }
+}
+
+/// What a single operation in a batch affects.
+///
+/// Operations on the same device are only coalesced into one
+/// frame (one SYN_REPORT) while their targets are distinct, since
+/// a frame can only carry one state change per key, axis or slot.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Key(u16),
+    Motion,
+    Wheel,
+    Slot(i32),
+    Pen,
+}
+
+/// Pending evdev events for a single device.
+struct Frame {
+    device: UinputDevice,
+    targets: Vec<Target>,
+    events: Vec<InputEvent>,
+    /// The encoder state from before the frame, restored if the frame
+    /// is never written.
+    saved: EncoderState,
+}
+
+/// The state that encoding operations updates.
+#[derive(Clone)]
+struct EncoderState {
+    touches: TouchState,
+    pen: Option<PenState>,
+    wheel: (i32, i32),
+}
+
+impl PlatformImpl {
+    fn geometry(&self) -> RwLockReadGuard<'_, ScreenGeometry> {
+        self.geometry.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn conn(&self) -> Result<&RustConnection, SimulationError> {
+        self.conn.as_deref().ok_or(SimulationError::NoX11Connection)
+    }
+
+    /// Find a keycode that has no keysyms bound to it, which can be
+    /// borrowed to type arbitrary characters.
+    fn spare_keycode(&mut self) -> Result<u8, SimulationError> {
+        if let Some(keycode) = self.spare_keycode {
+            return Ok(keycode);
+        }
+        let conn = self.conn()?;
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per_keycode = mapping.keysyms_per_keycode as usize;
+        let keycode = mapping
+            .keysyms
+            .chunks(per_keycode)
+            .zip(min..=max)
+            .rev()
+            // Keycode 8 stands for KEY_RESERVED, which the keyboard
+            // cannot press.
+            .filter(|&(_, keycode)| keycode > 8)
+            .find(|(keysyms, _)| keysyms.iter().all(|&keysym| keysym == 0))
+            .map(|(_, keycode)| keycode)
+            .ok_or(SimulationError::NoSpareKeycode)?;
+        self.spare_keycode = Some(keycode);
+        Ok(keycode)
+    }
+
+    /// Write a complete frame to a device.
+    fn write(
+        &mut self,
+        device: UinputDevice,
+        events: &[InputEvent],
+    ) -> Result<(), SimulationError> {
+        let class = device.class();
+        let virtual_device = match device {
+            UinputDevice::Keyboard => self.keyboard_device.as_mut(),
+            UinputDevice::RelativeMouse => self.rel_mouse_device.as_mut(),
+            UinputDevice::AbsoluteMouse => self.abs_mouse_device.as_mut(),
+            UinputDevice::Touchscreen => self.touch_device.as_mut(),
+            UinputDevice::Pen => self.pen_device.as_mut(),
+        };
+        let virtual_device = virtual_device.ok_or(SimulationError::DeviceDisabled(class))?;
+        virtual_device.emit(events)?;
         Ok(())
     }
 
-    pub(crate) fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+    /// Complete the events of a frame. Touchscreen frames end with
+    /// the state derived from all contacts, so this must run after
+    /// the last operation of the frame is encoded and before the
+    /// next one is.
+    fn finish_frame(&mut self, device: UinputDevice, events: &[InputEvent]) -> Vec<InputEvent> {
+        let mut events = events.to_vec();
+        if device == UinputDevice::Touchscreen {
+            events.extend(self.touches.frame_tail());
+        }
+        events
+    }
+
+    fn emit(&mut self, device: UinputDevice, events: &[InputEvent]) -> Result<(), SimulationError> {
+        let events = self.finish_frame(device, events);
+        self.write(device, &events)
+    }
+
+    /// Group a batch of operations into frames and pass each
+    /// complete frame to `write`.
+    ///
+    /// A pending frame is finished before the next operation is
+    /// encoded, so that the state derived from the touch contacts
+    /// reflects exactly the operations in the frame.
+    fn send_frames(
+        &mut self,
+        events: &[crate::InputEvent],
+        mut write: impl FnMut(&mut Self, UinputDevice, &[InputEvent]) -> Result<(), SimulationError>,
+    ) -> Result<(), SimulationError> {
+        let mut pending: Option<Frame> = None;
+        let mut flush = |platform: &mut Self, frame: Option<Frame>| match frame {
+            Some(frame) => {
+                let events = platform.finish_frame(frame.device, &frame.events);
+                let result = write(platform, frame.device, &events);
+                if result.is_err() {
+                    platform.restore(frame.saved);
+                }
+                result
+            }
+            None => Ok(()),
+        };
+        for event in events {
+            let Some((device, target)) = Self::route(event) else {
+                flush(self, pending.take())?;
+                dispatch(self, event)?;
+                continue;
+            };
+            let joinable = pending
+                .as_ref()
+                .is_some_and(|frame| frame.device == device && !frame.targets.contains(&target));
+            let mut frame = match pending.take() {
+                Some(frame) if joinable => frame,
+                previous => {
+                    flush(self, previous)?;
+                    Frame {
+                        device,
+                        targets: Vec::new(),
+                        events: Vec::new(),
+                        saved: self.save(),
+                    }
+                }
+            };
+            match self.encode(event) {
+                Ok(encoded) => {
+                    frame.targets.push(target);
+                    frame.events.extend(encoded);
+                    pending = Some(frame);
+                }
+                Err(err) => {
+                    self.restore(frame.saved);
+                    return Err(err);
+                }
+            }
+        }
+        flush(self, pending)
+    }
+
+    fn save(&self) -> EncoderState {
+        EncoderState {
+            touches: self.touches.clone(),
+            pen: self.pen,
+            wheel: (self.wheel_x, self.wheel_y),
+        }
+    }
+
+    fn restore(&mut self, state: EncoderState) {
+        self.touches = state.touches;
+        self.pen = state.pen;
+        (self.wheel_x, self.wheel_y) = state.wheel;
+    }
+
+    /// Encode and emit a single frame, restoring the encoder state
+    /// if the frame is not written.
+    fn emit_encoded(
+        &mut self,
+        device: UinputDevice,
+        encode: impl FnOnce(&mut Self) -> Result<Vec<InputEvent>, SimulationError>,
+    ) -> Result<(), SimulationError> {
+        let saved = self.save();
+        let result = encode(self).and_then(|events| self.emit(device, &events));
+        if result.is_err() {
+            self.restore(saved);
+        }
+        result
+    }
+
+    /// The area of the virtual screen covered by the range of a
+    /// device, as its position and size.
+    fn device_area(&self, device: UinputDevice) -> Result<(i32, i32, i32, i32), SimulationError> {
+        let geometry = self.geometry();
+        let Some(output) = self.outputs.get(&device) else {
+            let (width, height) = geometry.size;
+            return Ok((0, 0, width, height));
+        };
+        let monitor = geometry
+            .monitor(output)
+            .ok_or_else(|| SimulationError::UnknownOutput(output.clone()))?;
+        Ok((monitor.x, monitor.y, monitor.width, monitor.height))
+    }
+
+    /// The rectangle of a device, in device units, that covers a
+    /// screen area of the given size.
+    fn device_window(&self, device: UinputDevice, size: (i32, i32)) -> [f64; 4] {
+        let (max_x, max_y) = match device {
+            UinputDevice::Touchscreen => (self.touch_geometry.max_x, self.touch_geometry.max_y),
+            UinputDevice::Pen => match self.pen_mode {
+                PenMode::Display => (self.pen_geometry.max_x, self.pen_geometry.max_y),
+                PenMode::Opaque(mapping) => return mapping.window(&self.pen_geometry, size),
+            },
+            _ => (100_000, 100_000),
+        };
+        [0.0, 0.0, max_x as f64, max_y as f64]
+    }
+
+    fn scale_to_device(
+        &self,
+        device: UinputDevice,
+        x: i32,
+        y: i32,
+    ) -> Result<(i32, i32), SimulationError> {
+        let (left, top, width, height) = self.device_area(device)?;
+        let [x0, y0, x1, y1] = self.device_window(device, (width, height));
+        Ok((
+            (x0 + (x - left) as f64 / width as f64 * (x1 - x0)).round() as i32,
+            (y0 + (y - top) as f64 / height as f64 * (y1 - y0)).round() as i32,
+        ))
+    }
+
+    /// The uinput device an operation is performed on, and what it
+    /// affects there.
+    ///
+    /// Returns `None` for operations that are not performed through
+    /// uinput.
+    fn route(event: &crate::InputEvent) -> Option<(UinputDevice, Target)> {
+        use crate::InputEvent as E;
+        Some(match *event {
+            E::MoveMouseAbs { .. } => (UinputDevice::AbsoluteMouse, Target::Motion),
+            E::MoveMouseRel { .. } => (UinputDevice::RelativeMouse, Target::Motion),
+            E::Wheel { .. } => (UinputDevice::RelativeMouse, Target::Wheel),
+            E::KeyDown(key) | E::KeyUp(key) => {
+                (UinputDevice::Keyboard, Target::Key(KeyCode::from(key).0))
+            }
+            E::TouchDown { slot, .. } | E::TouchUp { slot } | E::TouchMove { slot, .. } => {
+                (UinputDevice::Touchscreen, Target::Slot(slot))
+            }
+            E::Pen(_) => (UinputDevice::Pen, Target::Pen),
+            E::MouseDown(button) | E::MouseUp(button) => (
+                UinputDevice::AbsoluteMouse,
+                Target::Key(KeyCode::from(button).0),
+            ),
+            E::TypeUnicode(_) => return None,
+        })
+    }
+
+    /// Translate an operation routed to uinput into the evdev events
+    /// that implement it, updating any internal state along the way.
+    fn encode(&mut self, event: &crate::InputEvent) -> Result<Vec<InputEvent>, SimulationError> {
+        use crate::InputEvent as E;
+        Ok(match *event {
+            E::MoveMouseAbs { x, y } => self.move_mouse_abs_events(x, y)?,
+            E::MoveMouseRel { x, y } => self.move_mouse_rel_events(x, y),
+            E::Wheel { x, y } => self.wheel_events(x, y),
+            E::KeyDown(key) => self.key_events(key, 1),
+            E::KeyUp(key) => self.key_events(key, 0),
+            E::TouchDown {
+                slot,
+                x,
+                y,
+                attributes,
+            } => self.touch_down_events(slot, x, y, attributes)?,
+            E::TouchUp { slot } => self.touch_up_events(slot)?,
+            E::TouchMove {
+                slot,
+                x,
+                y,
+                attributes,
+            } => self.touch_move_events(slot, x, y, attributes)?,
+            E::Pen(state) => self.pen_events(state)?,
+            E::MouseDown(button) => Self::button_events(button.into(), 1),
+            E::MouseUp(button) => Self::button_events(button.into(), 0),
+            E::TypeUnicode(_) => Vec::new(),
+        })
+    }
+
+    fn button_events(button: KeyCode, value: i32) -> Vec<InputEvent> {
+        vec![InputEvent::new(EventType::KEY.0, button.0, value)]
+    }
+
+    fn move_mouse_abs_events(
+        &mut self,
+        x: i32,
+        y: i32,
+    ) -> Result<Vec<InputEvent>, SimulationError> {
+        let (x, y) = self.scale_to_device(UinputDevice::AbsoluteMouse, x, y)?;
+        Ok(vec![
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, x),
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, y),
+        ])
+    }
+
+    fn move_mouse_rel_events(&mut self, x: i32, y: i32) -> Vec<InputEvent> {
+        vec![
+            InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, x),
+            InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_Y.0, y),
+        ]
+    }
+
+    fn wheel_events(&mut self, x: i32, y: i32) -> Vec<InputEvent> {
         self.wheel_x += x;
         self.wheel_y += y;
         let mut events = vec![
@@ -231,7 +1340,7 @@ impl PlatformImpl {
                 RelativeAxisCode::REL_HWHEEL.0,
                 self.wheel_x / 120,
             ));
-            self.wheel_x = self.wheel_x % 120;
+            self.wheel_x %= 120;
         }
         if self.wheel_y.abs() >= 120 {
             events.push(InputEvent::new(
@@ -239,36 +1348,43 @@ impl PlatformImpl {
                 RelativeAxisCode::REL_WHEEL.0,
                 self.wheel_y / 120,
             ));
-            self.wheel_y = self.wheel_y % 120;
+            self.wheel_y %= 120;
         }
-        self.rel_mouse_device.emit(&events)?;
-        Ok(())
+        events
     }
 
-    pub(crate) fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
-        self.keyboard_device
-            .emit(&[InputEvent::new(EventType::KEY.0, KeyCode::from(key).0, 1)])?;
-        Ok(())
+    fn key_events(&mut self, key: Key, value: i32) -> Vec<InputEvent> {
+        vec![InputEvent::new(
+            EventType::KEY.0,
+            KeyCode::from(key).0,
+            value,
+        )]
     }
 
-    pub(crate) fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
-        self.keyboard_device
-            .emit(&[InputEvent::new(EventType::KEY.0, KeyCode::from(key).0, 0)])?;
-        Ok(())
+    fn check_slot(&self, slot: i32) -> Result<(), SimulationError> {
+        if (0..self.touch_geometry.slots).contains(&slot) {
+            Ok(())
+        } else {
+            Err(SimulationError::InvalidSlot(slot))
+        }
     }
 
-    pub(crate) fn touch_down(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
-        let (width, height) = self.get_screen_size()?;
-        let (x, y) = (
-            (x as f64 / width as f64 * 100_000.0).round() as i32,
-            (y as f64 / height as f64 * 100_000.0).round() as i32,
-        );
-        self.touch_device.emit(&[
+    fn touch_down_events(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<Vec<InputEvent>, SimulationError> {
+        self.check_slot(slot)?;
+        let (x, y) = self.scale_to_device(UinputDevice::Touchscreen, x, y)?;
+        let tracking_id = self.touches.down(slot, (x, y));
+        let mut events = vec![
             InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_MT_SLOT.0, slot),
             InputEvent::new(
                 EventType::ABSOLUTE.0,
                 AbsoluteAxisCode::ABS_MT_TRACKING_ID.0,
-                slot,
+                tracking_id,
             ),
             InputEvent::new(
                 EventType::ABSOLUTE.0,
@@ -280,29 +1396,35 @@ impl PlatformImpl {
                 AbsoluteAxisCode::ABS_MT_POSITION_Y.0,
                 y,
             ),
-        ])?;
-        Ok(())
+        ];
+        events.extend(self.touch_attribute_events(attributes.or_defaults())?);
+        Ok(events)
     }
 
-    pub(crate) fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
-        self.touch_device.emit(&[
+    fn touch_up_events(&mut self, slot: i32) -> Result<Vec<InputEvent>, SimulationError> {
+        self.check_slot(slot)?;
+        self.touches.up(slot);
+        Ok(vec![
             InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_MT_SLOT.0, slot),
             InputEvent::new(
                 EventType::ABSOLUTE.0,
                 AbsoluteAxisCode::ABS_MT_TRACKING_ID.0,
                 -1,
             ),
-        ])?;
-        Ok(())
+        ])
     }
 
-    pub(crate) fn touch_move(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
-        let (width, height) = self.get_screen_size()?;
-        let (x, y) = (
-            (x as f64 / width as f64 * 100_000.0).round() as i32,
-            (y as f64 / height as f64 * 100_000.0).round() as i32,
-        );
-        self.touch_device.emit(&[
+    fn touch_move_events(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<Vec<InputEvent>, SimulationError> {
+        self.check_slot(slot)?;
+        let (x, y) = self.scale_to_device(UinputDevice::Touchscreen, x, y)?;
+        self.touches.moved(slot, (x, y));
+        let mut events = vec![
             InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_MT_SLOT.0, slot),
             InputEvent::new(
                 EventType::ABSOLUTE.0,
@@ -314,75 +1436,437 @@ impl PlatformImpl {
                 AbsoluteAxisCode::ABS_MT_POSITION_Y.0,
                 y,
             ),
-        ])?;
+        ];
+        events.extend(self.touch_attribute_events(attributes)?);
+        Ok(events)
+    }
+
+    /// The events for the attributes that are set, for the current
+    /// slot. Contact sizes use the horizontal scale of the device.
+    fn touch_attribute_events(
+        &self,
+        attributes: TouchAttributes,
+    ) -> Result<Vec<InputEvent>, SimulationError> {
+        let (_, _, width, _) = self.device_area(UinputDevice::Touchscreen)?;
+        let axis = |code: AbsoluteAxisCode, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+        };
+        let TouchscreenGeometry {
+            max_x,
+            max_pressure,
+            ..
+        } = self.touch_geometry;
+        let length = |pixels: i32| (pixels as f64 / width as f64 * max_x as f64).round() as i32;
+        let mut events = Vec::new();
+        if let Some(pressure) = attributes.pressure {
+            let pressure = (pressure.clamp(0.0, 1.0) * max_pressure as f64).round() as i32;
+            events.push(axis(AbsoluteAxisCode::ABS_MT_PRESSURE, pressure));
+        }
+        if let Some(major) = attributes.touch_major {
+            events.push(axis(AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR, length(major)));
+        }
+        if let Some(minor) = attributes.touch_minor {
+            events.push(axis(AbsoluteAxisCode::ABS_MT_TOUCH_MINOR, length(minor)));
+        }
+        if let Some(orientation) = attributes.orientation {
+            let orientation = orientation.clamp(-90, 90);
+            events.push(axis(AbsoluteAxisCode::ABS_MT_ORIENTATION, orientation));
+        }
+        if let Some(tool) = attributes.tool {
+            let tool = match tool {
+                TouchTool::Finger => MT_TOOL_FINGER,
+                TouchTool::Pen => MT_TOOL_PEN,
+                TouchTool::Palm => MT_TOOL_PALM,
+            };
+            events.push(axis(AbsoluteAxisCode::ABS_MT_TOOL_TYPE, tool));
+        }
+        Ok(events)
+    }
+
+    /// Leaving proximity, such as to switch between the pen and the
+    /// eraser, is reported in a frame of its own.
+    fn pen_events(&mut self, state: PenState) -> Result<Vec<InputEvent>, SimulationError> {
+        let key = |code: KeyCode, pressed: bool| {
+            InputEvent::new(EventType::KEY.0, code.0, pressed as i32)
+        };
+        let axis = |code: AbsoluteAxisCode, value: i32| {
+            InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+        };
+        let tool = |tool: PenTool| match tool {
+            PenTool::Pen => KeyCode::BTN_TOOL_PEN,
+            PenTool::Eraser => KeyCode::BTN_TOOL_RUBBER,
+        };
+
+        let mut events = vec![];
+        let mut previous = self.pen;
+        if let Some(last) = previous {
+            if !state.in_range || last.tool != state.tool {
+                events.extend([
+                    key(KeyCode::BTN_TOUCH, false),
+                    key(KeyCode::BTN_STYLUS, false),
+                    key(KeyCode::BTN_STYLUS2, false),
+                    axis(AbsoluteAxisCode::ABS_PRESSURE, 0),
+                    key(tool(last.tool), false),
+                ]);
+                if state.in_range {
+                    events.push(InputEvent::new(
+                        EventType::SYNCHRONIZATION.0,
+                        SynchronizationCode::SYN_REPORT.0,
+                        0,
+                    ));
+                }
+                previous = None;
+            }
+        }
+        if state.in_range {
+            if previous.is_none() {
+                events.push(key(tool(state.tool), true));
+            }
+            let (x, y) = self.scale_to_device(UinputDevice::Pen, state.x, state.y)?;
+            let (pressure, distance) = match state.contact {
+                true => (state.pressure, 0.0),
+                false => (0.0, state.distance),
+            };
+            let scale = |value: f64, max: i32| (value.clamp(0.0, 1.0) * max as f64).round() as i32;
+            let PenGeometry {
+                max_pressure,
+                max_tilt,
+                ..
+            } = self.pen_geometry;
+            events.extend([
+                axis(AbsoluteAxisCode::ABS_X, x),
+                axis(AbsoluteAxisCode::ABS_Y, y),
+                axis(
+                    AbsoluteAxisCode::ABS_PRESSURE,
+                    scale(pressure, max_pressure),
+                ),
+                axis(AbsoluteAxisCode::ABS_DISTANCE, scale(distance, 100_000)),
+                axis(
+                    AbsoluteAxisCode::ABS_TILT_X,
+                    state.tilt_x.clamp(-max_tilt, max_tilt),
+                ),
+                axis(
+                    AbsoluteAxisCode::ABS_TILT_Y,
+                    state.tilt_y.clamp(-max_tilt, max_tilt),
+                ),
+                axis(AbsoluteAxisCode::ABS_WHEEL, state.rotation.rem_euclid(360)),
+                key(KeyCode::BTN_TOUCH, state.contact),
+                key(KeyCode::BTN_STYLUS, state.barrel),
+                key(KeyCode::BTN_STYLUS2, state.barrel2),
+            ]);
+        }
+        self.pen = state.in_range.then_some(state);
+        Ok(events)
+    }
+}
+
+impl InputBackend for PlatformImpl {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        let events = self.move_mouse_abs_events(x, y)?;
+        self.emit(UinputDevice::AbsoluteMouse, &events)?;
+        Ok(())
+    }
+
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        let events = self.move_mouse_rel_events(x, y);
+        self.emit(UinputDevice::RelativeMouse, &events)?;
+        Ok(())
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.emit(
+            UinputDevice::AbsoluteMouse,
+            &[InputEvent::new(
+                EventType::KEY.0,
+                KeyCode::from(button).0,
+                1,
+            )],
+        )?;
         Ok(())
     }
 
-    pub(crate) fn pen(
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.emit(
+            UinputDevice::AbsoluteMouse,
+            &[InputEvent::new(
+                EventType::KEY.0,
+                KeyCode::from(button).0,
+                0,
+            )],
+        )?;
+        Ok(())
+    }
+
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.emit_encoded(UinputDevice::RelativeMouse, |platform| {
+            Ok(platform.wheel_events(x, y))
+        })
+    }
+
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+        let events = self.key_events(key, 1);
+        self.emit(UinputDevice::Keyboard, &events)?;
+        Ok(())
+    }
+
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+        let events = self.key_events(key, 0);
+        self.emit(UinputDevice::Keyboard, &events)?;
+        Ok(())
+    }
+
+    fn touch_down(
         &mut self,
+        slot: i32,
         x: i32,
         y: i32,
-        pressure: f64,
-        tilt_x: i32,
-        tilt_y: i32,
+        attributes: TouchAttributes,
     ) -> Result<(), SimulationError> {
-        let (width, height) = self.get_screen_size()?;
-        let (x, y) = (
-            (x as f64 / width as f64 * 100_000.0).round() as i32,
-            (y as f64 / height as f64 * 100_000.0).round() as i32,
-        );
-        let scaled_pressure = (pressure * 100_000.0).round() as i32;
-        let mut events = vec![];
-        if self.last_pressure < 0.00001 && pressure >= 0.00001 {
-            events.push(InputEvent::new(
-                EventType::KEY.0,
-                KeyCode::BTN_TOOL_PEN.0,
-                1,
-            ));
-            events.push(InputEvent::new(EventType::KEY.0, KeyCode::BTN_TOUCH.0, 1));
+        self.emit_encoded(UinputDevice::Touchscreen, |platform| {
+            platform.touch_down_events(slot, x, y, attributes)
+        })
+    }
+
+    fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
+        self.emit_encoded(UinputDevice::Touchscreen, |platform| {
+            platform.touch_up_events(slot)
+        })
+    }
+
+    fn touch_move(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.emit_encoded(UinputDevice::Touchscreen, |platform| {
+            platform.touch_move_events(slot, x, y, attributes)
+        })
+    }
+
+    /// Emits every update in a single evdev frame, even when a slot
+    /// appears more than once.
+    fn touch_frame(&mut self, contacts: &[TouchContact]) -> Result<(), SimulationError> {
+        self.emit_encoded(UinputDevice::Touchscreen, |platform| {
+            let mut events = Vec::new();
+            for contact in contacts {
+                events.extend(platform.encode(&contact.event())?);
+            }
+            Ok(events)
+        })
+    }
+
+    fn pen(&mut self, state: PenState) -> Result<(), SimulationError> {
+        self.emit_encoded(UinputDevice::Pen, |platform| platform.pen_events(state))
+    }
+
+    /// Consecutive operations on the same uinput device are emitted
+    /// together, as a single frame, unless two of them affect the
+    /// same key, axis or touch slot.
+    fn send(&mut self, events: &[crate::InputEvent]) -> Result<(), SimulationError> {
+        self.send_frames(events, Self::write)
+    }
+
+    /// Temporarily binds the character's keysym to an unused keycode
+    /// and presses that keycode on the uinput keyboard, so that the
+    /// character arrives in order with the keys typed around it.
+    fn type_unicode(&mut self, c: char) -> Result<(), SimulationError> {
+        if self.keyboard_device.is_none() {
+            return Err(SimulationError::DeviceDisabled(DeviceClass::Keyboard));
         }
-        events.push(InputEvent::new(
-            EventType::ABSOLUTE.0,
-            AbsoluteAxisCode::ABS_X.0,
-            x,
-        ));
-        events.push(InputEvent::new(
-            EventType::ABSOLUTE.0,
-            AbsoluteAxisCode::ABS_Y.0,
-            y,
-        ));
-        events.push(InputEvent::new(
-            EventType::ABSOLUTE.0,
-            AbsoluteAxisCode::ABS_PRESSURE.0,
-            scaled_pressure,
-        ));
-        events.push(InputEvent::new(
-            EventType::ABSOLUTE.0,
-            AbsoluteAxisCode::ABS_TILT_X.0,
-            tilt_x,
-        ));
-        events.push(InputEvent::new(
-            EventType::ABSOLUTE.0,
-            AbsoluteAxisCode::ABS_TILT_Y.0,
-            tilt_y,
-        ));
-        if self.last_pressure >= 0.00001 && pressure < 0.00001 {
-            events.push(InputEvent::new(EventType::KEY.0, KeyCode::BTN_TOUCH.0, 0));
-            events.push(InputEvent::new(
-                EventType::KEY.0,
-                KeyCode::BTN_TOOL_PEN.0,
-                0,
-            ));
+        let keycode = self.spare_keycode()?;
+        let conn = self.conn.clone().ok_or(SimulationError::NoX11Connection)?;
+        let keysym = char_to_keysym(c);
+        // Bind both levels so that a held Shift does not matter.
+        conn.change_keyboard_mapping(1, keycode, 2, &[keysym, keysym])?
+            .check()?;
+        // Wait for the server to process the new mapping.
+        conn.get_input_focus()?.reply()?;
+        // X keycodes are evdev key codes offset by 8.
+        let code = KeyCode::new(u16::from(keycode) - 8);
+        let pressed = self
+            .emit(
+                UinputDevice::Keyboard,
+                &[InputEvent::new(EventType::KEY.0, code.0, 1)],
+            )
+            .and_then(|()| {
+                self.emit(
+                    UinputDevice::Keyboard,
+                    &[InputEvent::new(EventType::KEY.0, code.0, 0)],
+                )
+            });
+        // The server reads the keyboard asynchronously, and clients
+        // look up the keysym when they process the event, so give
+        // both a moment before taking the mapping away again.
+        std::thread::sleep(UNICODE_RESTORE_DELAY);
+        conn.change_keyboard_mapping(1, keycode, 2, &[0, 0])?
+            .check()?;
+        conn.flush()?;
+        pressed
+    }
+
+    fn can_type_unicode(&self) -> bool {
+        self.conn.is_some() && self.keyboard_device.is_some()
+    }
+
+    /// Builds the layout from the core keyboard mapping of the X
+    /// server, using the first keyboard group.
+    fn keyboard_layout(&self) -> Result<Option<KeyboardLayout>, SimulationError> {
+        let Some(conn) = self.conn.as_deref() else {
+            return Ok(None);
+        };
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per_keycode = mapping.keysyms_per_keycode as usize;
+
+        // X keycodes are evdev key codes offset by 8.
+        let mut keys = HashMap::new();
+        for key in Key::iter() {
+            keys.entry(KeyCode::from(key).code() + 8).or_insert(key);
         }
-        self.pen_device.emit(&events)?;
-        self.last_pressure = pressure;
-        Ok(())
+
+        let mut layout = KeyboardLayout::new();
+        // The third and fourth levels are only reachable through the
+        // key that carries ISO_Level3_Shift, wherever it is.
+        let level3 = mapping
+            .keysyms
+            .chunks(per_keycode)
+            .zip(min..=max)
+            .find(|(keysyms, _)| keysyms.contains(&ISO_LEVEL3_SHIFT))
+            .and_then(|(_, keycode)| keys.get(&u16::from(keycode)).copied());
+        if let Some(key) = level3 {
+            layout.set_alt_gr_key(key);
+        }
+        // Visit the levels from the fewest modifiers to the most, so
+        // that every character uses the simplest stroke available.
+        for (level, modifiers) in KEYSYM_LEVELS {
+            if modifiers.alt_gr && level3.is_none() {
+                continue;
+            }
+            for (keysyms, keycode) in mapping.keysyms.chunks(per_keycode).zip(min..=max) {
+                let Some(&key) = keys.get(&u16::from(keycode)) else {
+                    continue;
+                };
+                let Some(c) = keysyms.get(level).copied().and_then(keysym_to_char) else {
+                    continue;
+                };
+                if layout.stroke(c).is_none() {
+                    layout.insert(c, KeyStroke::new(key, modifiers));
+                }
+            }
+        }
+        Ok(Some(layout))
+    }
+
+    /// Returns the cached size, which is updated when RandR reports
+    /// a change.
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
+        Ok(self.geometry().size)
+    }
+
+    /// Lists the active RandR outputs, or a single monitor covering
+    /// the screen when headless.
+    fn monitors(&self) -> Result<Vec<Monitor>, SimulationError> {
+        Ok(self.geometry().monitors.clone())
+    }
+
+    /// Queries the pointer of the X server, or returns `None` when
+    /// headless.
+    fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        let Some(conn) = self.conn.as_deref() else {
+            return Ok(None);
+        };
+        let root_window = conn.setup().roots[0].root;
+        let pointer = conn.query_pointer(root_window)?.reply()?;
+        Ok(Some((pointer.root_x.into(), pointer.root_y.into())))
+    }
+
+    /// Reads the button state of the X server's core pointer, or
+    /// returns `None` when headless. The core protocol only reports
+    /// the left, middle and right buttons.
+    fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        let Some(conn) = self.conn.as_deref() else {
+            return Ok(None);
+        };
+        let root_window = conn.setup().roots[0].root;
+        let pointer = conn.query_pointer(root_window)?.reply()?;
+        let buttons = [
+            (xproto::KeyButMask::BUTTON1, MouseButton::Left),
+            (xproto::KeyButMask::BUTTON2, MouseButton::Middle),
+            (xproto::KeyButMask::BUTTON3, MouseButton::Right),
+        ];
+        Ok(Some(
+            buttons
+                .into_iter()
+                .filter(|&(mask, _)| pointer.mask.contains(mask))
+                .map(|(_, button)| button)
+                .collect(),
+        ))
+    }
+}
+
+/// How long a borrowed keycode stays bound after typing a character.
+const UNICODE_RESTORE_DELAY: Duration = Duration::from_millis(50);
+
+/// The keysym of the key that selects the third shift level, AltGr.
+const ISO_LEVEL3_SHIFT: u32 = 0xfe03;
+
+/// The positions of the first group's shift levels within a keycode's
+/// entry in the core keyboard mapping, and the modifiers that select
+/// them.
+const KEYSYM_LEVELS: [(usize, Modifiers); 4] = [
+    (0, Modifiers::NONE),
+    (1, Modifiers::SHIFT),
+    (4, Modifiers::ALT_GR),
+    (
+        5,
+        Modifiers {
+            shift: true,
+            alt_gr: true,
+        },
+    ),
+];
+
+/// The character produced by an X11 keysym, if any.
+///
+/// Only Latin-1, Unicode and a few function keysyms are recognized.
+/// Legacy keysyms for other scripts are ignored.
+fn keysym_to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        // Currency symbols share their code points.
+        0x20a0..=0x20ac => char::from_u32(keysym),
+        0x0100_0100..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+        0xff09 => Some('\t'),
+        0xff0d => Some('\n'),
+        _ => None,
     }
+}
 
-    pub(crate) fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
-        let root_window = self.conn.setup().roots[0].root;
-        let geometry = self.conn.get_geometry(root_window)?.reply()?;
-        Ok((geometry.width as _, geometry.height as _))
+/// The X11 keysym for a character.
+fn char_to_keysym(c: char) -> u32 {
+    match c as u32 {
+        // Latin-1 keysyms are identical to their code points.
+        cp @ (0x20..=0x7e | 0xa0..=0xff) => cp,
+        cp => 0x0100_0000 | cp,
+    }
+}
+
+impl From<MouseButton> for KeyCode {
+    fn from(button: MouseButton) -> Self {
+        match button {
+            MouseButton::Left => KeyCode::BTN_LEFT,
+            MouseButton::Right => KeyCode::BTN_RIGHT,
+            MouseButton::Middle => KeyCode::BTN_MIDDLE,
+            MouseButton::Side => KeyCode::BTN_SIDE,
+            MouseButton::Extra => KeyCode::BTN_EXTRA,
+            MouseButton::Forward => KeyCode::BTN_FORWARD,
+            MouseButton::Back => KeyCode::BTN_BACK,
+            MouseButton::Task => KeyCode::BTN_TASK,
+            MouseButton::Raw(code) => KeyCode::new(code),
+        }
     }
 }
 
@@ -488,6 +1972,7 @@ impl From<Key> for KeyCode {
             Key::KpSlash => KeyCode::KEY_KPSLASH,
             Key::SysRq => KeyCode::KEY_SYSRQ,
             Key::RightAlt => KeyCode::KEY_RIGHTALT,
+            Key::LineFeed => KeyCode::KEY_LINEFEED,
             Key::Home => KeyCode::KEY_HOME,
             Key::Up => KeyCode::KEY_UP,
             Key::PageUp => KeyCode::KEY_PAGEUP,
@@ -506,6 +1991,7 @@ impl From<Key> for KeyCode {
             Key::KpEqual => KeyCode::KEY_KPEQUAL,
             Key::KpPlusMinus => KeyCode::KEY_KPPLUSMINUS,
             Key::Pause => KeyCode::KEY_PAUSE,
+            Key::Scale => KeyCode::KEY_SCALE,
             Key::KpComma => KeyCode::KEY_KPCOMMA,
             Key::Hanguel => KeyCode::KEY_HANGEUL,
             Key::Hanja => KeyCode::KEY_HANJA,
@@ -514,36 +2000,647 @@ impl From<Key> for KeyCode {
             Key::RightMeta => KeyCode::KEY_RIGHTMETA,
             Key::Compose => KeyCode::KEY_COMPOSE,
             Key::Stop => KeyCode::KEY_STOP,
+            Key::Again => KeyCode::KEY_AGAIN,
+            Key::Props => KeyCode::KEY_PROPS,
+            Key::Undo => KeyCode::KEY_UNDO,
+            Key::Front => KeyCode::KEY_FRONT,
+            Key::Copy => KeyCode::KEY_COPY,
+            Key::Open => KeyCode::KEY_OPEN,
+            Key::Paste => KeyCode::KEY_PASTE,
+            Key::Find => KeyCode::KEY_FIND,
+            Key::Cut => KeyCode::KEY_CUT,
             Key::Help => KeyCode::KEY_HELP,
+            Key::Menu => KeyCode::KEY_MENU,
             Key::Calc => KeyCode::KEY_CALC,
+            Key::Setup => KeyCode::KEY_SETUP,
             Key::Sleep => KeyCode::KEY_SLEEP,
             Key::WakeUp => KeyCode::KEY_WAKEUP,
+            Key::File => KeyCode::KEY_FILE,
+            Key::SendFile => KeyCode::KEY_SENDFILE,
+            Key::DeleteFile => KeyCode::KEY_DELETEFILE,
+            Key::Xfer => KeyCode::KEY_XFER,
+            Key::Prog1 => KeyCode::KEY_PROG1,
+            Key::Prog2 => KeyCode::KEY_PROG2,
+            Key::Www => KeyCode::KEY_WWW,
+            Key::MsDos => KeyCode::KEY_MSDOS,
+            Key::ScreenLock => KeyCode::KEY_COFFEE,
+            Key::RotateDisplay => KeyCode::KEY_ROTATE_DISPLAY,
+            Key::CycleWindows => KeyCode::KEY_CYCLEWINDOWS,
             Key::Mail => KeyCode::KEY_MAIL,
             Key::Bookmarks => KeyCode::KEY_BOOKMARKS,
             Key::Computer => KeyCode::KEY_COMPUTER,
             Key::Back => KeyCode::KEY_BACK,
             Key::Forward => KeyCode::KEY_FORWARD,
+            Key::CloseCD => KeyCode::KEY_CLOSECD,
+            Key::EjectCD => KeyCode::KEY_EJECTCD,
+            Key::EjectCloseCD => KeyCode::KEY_EJECTCLOSECD,
             Key::NextSong => KeyCode::KEY_NEXTSONG,
             Key::PlayPause => KeyCode::KEY_PLAYPAUSE,
             Key::PreviousSong => KeyCode::KEY_PREVIOUSSONG,
             Key::StopCD => KeyCode::KEY_STOPCD,
+            Key::Record => KeyCode::KEY_RECORD,
+            Key::Rewind => KeyCode::KEY_REWIND,
+            Key::Phone => KeyCode::KEY_PHONE,
+            Key::Iso => KeyCode::KEY_ISO,
+            Key::Config => KeyCode::KEY_CONFIG,
             Key::Homepage => KeyCode::KEY_HOMEPAGE,
             Key::Refresh => KeyCode::KEY_REFRESH,
+            Key::Exit => KeyCode::KEY_EXIT,
+            Key::Move => KeyCode::KEY_MOVE,
+            Key::Edit => KeyCode::KEY_EDIT,
+            Key::ScrollUp => KeyCode::KEY_SCROLLUP,
+            Key::ScrollDown => KeyCode::KEY_SCROLLDOWN,
+            Key::KpLeftParen => KeyCode::KEY_KPLEFTPAREN,
+            Key::KpRightParen => KeyCode::KEY_KPRIGHTPAREN,
+            Key::New => KeyCode::KEY_NEW,
+            Key::Redo => KeyCode::KEY_REDO,
             Key::F13 => KeyCode::KEY_F13,
             Key::F14 => KeyCode::KEY_F14,
             Key::F15 => KeyCode::KEY_F15,
+            Key::F16 => KeyCode::KEY_F16,
+            Key::F17 => KeyCode::KEY_F17,
+            Key::F18 => KeyCode::KEY_F18,
+            Key::F19 => KeyCode::KEY_F19,
+            Key::F20 => KeyCode::KEY_F20,
+            Key::F21 => KeyCode::KEY_F21,
+            Key::F22 => KeyCode::KEY_F22,
             Key::F23 => KeyCode::KEY_F23,
+            Key::F24 => KeyCode::KEY_F24,
+            Key::PlayCD => KeyCode::KEY_PLAYCD,
+            Key::PauseCD => KeyCode::KEY_PAUSECD,
+            Key::Prog3 => KeyCode::KEY_PROG3,
+            Key::Prog4 => KeyCode::KEY_PROG4,
+            Key::AllApplications => KeyCode::KEY_DASHBOARD,
+            Key::Suspend => KeyCode::KEY_SUSPEND,
+            Key::Close => KeyCode::KEY_CLOSE,
+            Key::Play => KeyCode::KEY_PLAY,
+            Key::FastForward => KeyCode::KEY_FASTFORWARD,
+            Key::BassBoost => KeyCode::KEY_BASSBOOST,
+            Key::Print => KeyCode::KEY_PRINT,
+            Key::Hp => KeyCode::KEY_HP,
             Key::Camera => KeyCode::KEY_CAMERA,
+            Key::Sound => KeyCode::KEY_SOUND,
+            Key::Question => KeyCode::KEY_QUESTION,
+            Key::Email => KeyCode::KEY_EMAIL,
+            Key::Chat => KeyCode::KEY_CHAT,
             Key::Search => KeyCode::KEY_SEARCH,
+            Key::Connect => KeyCode::KEY_CONNECT,
+            Key::Finance => KeyCode::KEY_FINANCE,
+            Key::Sport => KeyCode::KEY_SPORT,
+            Key::Shop => KeyCode::KEY_SHOP,
+            Key::AltErase => KeyCode::KEY_ALTERASE,
+            Key::Cancel => KeyCode::KEY_CANCEL,
             Key::BrightnessDown => KeyCode::KEY_BRIGHTNESSDOWN,
             Key::BrightnessUp => KeyCode::KEY_BRIGHTNESSUP,
             Key::Media => KeyCode::KEY_MEDIA,
             Key::SwitchVideoMode => KeyCode::KEY_SWITCHVIDEOMODE,
+            Key::KbdIllumToggle => KeyCode::KEY_KBDILLUMTOGGLE,
+            Key::KbdIllumDown => KeyCode::KEY_KBDILLUMDOWN,
+            Key::KbdIllumUp => KeyCode::KEY_KBDILLUMUP,
+            Key::Send => KeyCode::KEY_SEND,
+            Key::Reply => KeyCode::KEY_REPLY,
+            Key::ForwardMail => KeyCode::KEY_FORWARDMAIL,
+            Key::Save => KeyCode::KEY_SAVE,
+            Key::Documents => KeyCode::KEY_DOCUMENTS,
             Key::Battery => KeyCode::KEY_BATTERY,
+            Key::Bluetooth => KeyCode::KEY_BLUETOOTH,
             Key::Wlan => KeyCode::KEY_WLAN,
+            Key::Uwb => KeyCode::KEY_UWB,
+            Key::VideoNext => KeyCode::KEY_VIDEO_NEXT,
+            Key::VideoPrev => KeyCode::KEY_VIDEO_PREV,
+            Key::BrightnessCycle => KeyCode::KEY_BRIGHTNESS_CYCLE,
+            Key::BrightnessZero => KeyCode::KEY_BRIGHTNESS_AUTO,
+            Key::DisplayOff => KeyCode::KEY_DISPLAY_OFF,
+            Key::Wwan => KeyCode::KEY_WWAN,
+            Key::RfKill => KeyCode::KEY_RFKILL,
+            Key::MicMute => KeyCode::KEY_MICMUTE,
+            Key::Ok => KeyCode::KEY_OK,
+            Key::Select => KeyCode::KEY_SELECT,
+            Key::Goto => KeyCode::KEY_GOTO,
+            Key::Clear => KeyCode::KEY_CLEAR,
+            Key::Power2 => KeyCode::KEY_POWER2,
+            Key::OptionKey => KeyCode::KEY_OPTION,
+            Key::Info => KeyCode::KEY_INFO,
+            Key::Time => KeyCode::KEY_TIME,
+            Key::Vendor => KeyCode::KEY_VENDOR,
+            Key::Archive => KeyCode::KEY_ARCHIVE,
+            Key::Program => KeyCode::KEY_PROGRAM,
+            Key::Channel => KeyCode::KEY_CHANNEL,
+            Key::Favorites => KeyCode::KEY_FAVORITES,
+            Key::Epg => KeyCode::KEY_EPG,
+            Key::Pvr => KeyCode::KEY_PVR,
+            Key::Mhp => KeyCode::KEY_MHP,
+            Key::Language => KeyCode::KEY_LANGUAGE,
+            Key::Title => KeyCode::KEY_TITLE,
+            Key::Subtitle => KeyCode::KEY_SUBTITLE,
+            Key::Angle => KeyCode::KEY_ANGLE,
+            Key::FullScreen => KeyCode::KEY_FULL_SCREEN,
+            Key::Mode => KeyCode::KEY_MODE,
+            Key::Keyboard => KeyCode::KEY_KEYBOARD,
+            Key::AspectRatio => KeyCode::KEY_SCREEN,
+            Key::Pc => KeyCode::KEY_PC,
+            Key::Tv => KeyCode::KEY_TV,
+            Key::Tv2 => KeyCode::KEY_TV2,
+            Key::Vcr => KeyCode::KEY_VCR,
+            Key::Vcr2 => KeyCode::KEY_VCR2,
+            Key::Sat => KeyCode::KEY_SAT,
+            Key::Sat2 => KeyCode::KEY_SAT2,
+            Key::Cd => KeyCode::KEY_CD,
+            Key::Tape => KeyCode::KEY_TAPE,
+            Key::Radio => KeyCode::KEY_RADIO,
+            Key::Tuner => KeyCode::KEY_TUNER,
+            Key::Player => KeyCode::KEY_PLAYER,
+            Key::Text => KeyCode::KEY_TEXT,
             Key::Dvd => KeyCode::KEY_DVD,
+            Key::Aux => KeyCode::KEY_AUX,
+            Key::Mp3 => KeyCode::KEY_MP3,
+            Key::Audio => KeyCode::KEY_AUDIO,
+            Key::Video => KeyCode::KEY_VIDEO,
+            Key::Directory => KeyCode::KEY_DIRECTORY,
+            Key::List => KeyCode::KEY_LIST,
+            Key::Memo => KeyCode::KEY_MEMO,
+            Key::Calendar => KeyCode::KEY_CALENDAR,
+            Key::Red => KeyCode::KEY_RED,
+            Key::Green => KeyCode::KEY_GREEN,
+            Key::Yellow => KeyCode::KEY_YELLOW,
+            Key::Blue => KeyCode::KEY_BLUE,
+            Key::ChannelUp => KeyCode::KEY_CHANNELUP,
+            Key::ChannelDown => KeyCode::KEY_CHANNELDOWN,
+            Key::First => KeyCode::KEY_FIRST,
+            Key::Last => KeyCode::KEY_LAST,
+            Key::Ab => KeyCode::KEY_AB,
+            Key::Next => KeyCode::KEY_NEXT,
+            Key::Restart => KeyCode::KEY_RESTART,
+            Key::Slow => KeyCode::KEY_SLOW,
+            Key::Shuffle => KeyCode::KEY_SHUFFLE,
+            Key::Break => KeyCode::KEY_BREAK,
+            Key::Previous => KeyCode::KEY_PREVIOUS,
+            Key::Digits => KeyCode::KEY_DIGITS,
+            Key::Teen => KeyCode::KEY_TEEN,
+            Key::Twen => KeyCode::KEY_TWEN,
+            Key::VideoPhone => KeyCode::KEY_VIDEOPHONE,
+            Key::Games => KeyCode::KEY_GAMES,
+            Key::ZoomIn => KeyCode::KEY_ZOOMIN,
+            Key::ZoomOut => KeyCode::KEY_ZOOMOUT,
+            Key::ZoomReset => KeyCode::KEY_ZOOMRESET,
+            Key::WordProcessor => KeyCode::KEY_WORDPROCESSOR,
+            Key::Editor => KeyCode::KEY_EDITOR,
+            Key::Spreadsheet => KeyCode::KEY_SPREADSHEET,
+            Key::GraphicsEditor => KeyCode::KEY_GRAPHICSEDITOR,
+            Key::Presentation => KeyCode::KEY_PRESENTATION,
+            Key::Database => KeyCode::KEY_DATABASE,
+            Key::News => KeyCode::KEY_NEWS,
+            Key::VoiceMail => KeyCode::KEY_VOICEMAIL,
+            Key::AddressBook => KeyCode::KEY_ADDRESSBOOK,
+            Key::Messenger => KeyCode::KEY_MESSENGER,
+            Key::DisplayToggle => KeyCode::KEY_DISPLAYTOGGLE,
+            Key::SpellCheck => KeyCode::KEY_SPELLCHECK,
+            Key::LogOff => KeyCode::KEY_LOGOFF,
+            Key::Dollar => KeyCode::KEY_DOLLAR,
+            Key::Euro => KeyCode::KEY_EURO,
+            Key::FrameBack => KeyCode::KEY_FRAMEBACK,
+            Key::FrameForward => KeyCode::KEY_FRAMEFORWARD,
+            Key::ContextMenu => KeyCode::KEY_CONTEXT_MENU,
+            Key::MediaRepeat => KeyCode::KEY_MEDIA_REPEAT,
+            Key::TenChannelsUp => KeyCode::KEY_10CHANNELSUP,
+            Key::TenChannelsDown => KeyCode::KEY_10CHANNELSDOWN,
+            Key::Images => KeyCode::KEY_IMAGES,
+            Key::NotificationCenter => KeyCode::new(0x1bc), // KEY_NOTIFICATION_CENTER
+            Key::PickupPhone => KeyCode::KEY_PICKUP_PHONE,
+            Key::HangupPhone => KeyCode::KEY_HANGUP_PHONE,
+            Key::LinkPhone => KeyCode::new(0x1bf), // KEY_LINK_PHONE
+            Key::DelEol => KeyCode::KEY_DEL_EOL,
+            Key::DelEos => KeyCode::KEY_DEL_EOS,
+            Key::InsLine => KeyCode::KEY_INS_LINE,
+            Key::DelLine => KeyCode::KEY_DEL_LINE,
+            Key::Fn => KeyCode::KEY_FN,
             Key::FnEsc => KeyCode::KEY_FN_ESC,
-            _ => KeyCode::KEY_UNKNOWN,
+            Key::FnF1 => KeyCode::KEY_FN_F1,
+            Key::FnF2 => KeyCode::KEY_FN_F2,
+            Key::FnF3 => KeyCode::KEY_FN_F3,
+            Key::FnF4 => KeyCode::KEY_FN_F4,
+            Key::FnF5 => KeyCode::KEY_FN_F5,
+            Key::FnF6 => KeyCode::KEY_FN_F6,
+            Key::FnF7 => KeyCode::KEY_FN_F7,
+            Key::FnF8 => KeyCode::KEY_FN_F8,
+            Key::FnF9 => KeyCode::KEY_FN_F9,
+            Key::FnF10 => KeyCode::KEY_FN_F10,
+            Key::FnF11 => KeyCode::KEY_FN_F11,
+            Key::FnF12 => KeyCode::KEY_FN_F12,
+            Key::Fn1 => KeyCode::KEY_FN_1,
+            Key::Fn2 => KeyCode::KEY_FN_2,
+            Key::FnD => KeyCode::KEY_FN_D,
+            Key::FnE => KeyCode::KEY_FN_E,
+            Key::FnF => KeyCode::KEY_FN_F,
+            Key::FnS => KeyCode::KEY_FN_S,
+            Key::FnB => KeyCode::KEY_FN_B,
+            Key::FnRightShift => KeyCode::new(0x1e5), // KEY_FN_RIGHT_SHIFT
+            Key::BrlDot1 => KeyCode::KEY_BRL_DOT1,
+            Key::BrlDot2 => KeyCode::KEY_BRL_DOT2,
+            Key::BrlDot3 => KeyCode::KEY_BRL_DOT3,
+            Key::BrlDot4 => KeyCode::KEY_BRL_DOT4,
+            Key::BrlDot5 => KeyCode::KEY_BRL_DOT5,
+            Key::BrlDot6 => KeyCode::KEY_BRL_DOT6,
+            Key::BrlDot7 => KeyCode::KEY_BRL_DOT7,
+            Key::BrlDot8 => KeyCode::KEY_BRL_DOT8,
+            Key::BrlDot9 => KeyCode::KEY_BRL_DOT9,
+            Key::BrlDot10 => KeyCode::KEY_BRL_DOT10,
+            Key::Numeric0 => KeyCode::KEY_NUMERIC_0,
+            Key::Numeric1 => KeyCode::KEY_NUMERIC_1,
+            Key::Numeric2 => KeyCode::KEY_NUMERIC_2,
+            Key::Numeric3 => KeyCode::KEY_NUMERIC_3,
+            Key::Numeric4 => KeyCode::KEY_NUMERIC_4,
+            Key::Numeric5 => KeyCode::KEY_NUMERIC_5,
+            Key::Numeric6 => KeyCode::KEY_NUMERIC_6,
+            Key::Numeric7 => KeyCode::KEY_NUMERIC_7,
+            Key::Numeric8 => KeyCode::KEY_NUMERIC_8,
+            Key::Numeric9 => KeyCode::KEY_NUMERIC_9,
+            Key::NumericStar => KeyCode::KEY_NUMERIC_STAR,
+            Key::NumericPound => KeyCode::KEY_NUMERIC_POUND,
+            Key::NumericA => KeyCode::KEY_NUMERIC_A,
+            Key::NumericB => KeyCode::KEY_NUMERIC_B,
+            Key::NumericC => KeyCode::KEY_NUMERIC_C,
+            Key::NumericD => KeyCode::KEY_NUMERIC_D,
+            Key::CameraFocus => KeyCode::KEY_CAMERA_FOCUS,
+            Key::WpsButton => KeyCode::KEY_WPS_BUTTON,
+            Key::TouchpadToggle => KeyCode::KEY_TOUCHPAD_TOGGLE,
+            Key::TouchpadOn => KeyCode::KEY_TOUCHPAD_ON,
+            Key::TouchpadOff => KeyCode::KEY_TOUCHPAD_OFF,
+            Key::CameraZoomIn => KeyCode::KEY_CAMERA_ZOOMIN,
+            Key::CameraZoomOut => KeyCode::KEY_CAMERA_ZOOMOUT,
+            Key::CameraUp => KeyCode::KEY_CAMERA_UP,
+            Key::CameraDown => KeyCode::KEY_CAMERA_DOWN,
+            Key::CameraLeft => KeyCode::KEY_CAMERA_LEFT,
+            Key::CameraRight => KeyCode::KEY_CAMERA_RIGHT,
+            Key::AttendantOn => KeyCode::KEY_ATTENDANT_ON,
+            Key::AttendantOff => KeyCode::KEY_ATTENDANT_OFF,
+            Key::AttendantToggle => KeyCode::KEY_ATTENDANT_TOGGLE,
+            Key::LightsToggle => KeyCode::KEY_LIGHTS_TOGGLE,
+            Key::AlsToggle => KeyCode::KEY_ALS_TOGGLE,
+            Key::RotateLockToggle => KeyCode::new(0x231), // KEY_ROTATE_LOCK_TOGGLE
+            Key::RefreshRateToggle => KeyCode::new(0x232), // KEY_REFRESH_RATE_TOGGLE
+            Key::ButtonConfig => KeyCode::KEY_BUTTONCONFIG,
+            Key::TaskManager => KeyCode::KEY_TASKMANAGER,
+            Key::Journal => KeyCode::KEY_JOURNAL,
+            Key::ControlPanel => KeyCode::KEY_CONTROLPANEL,
+            Key::AppSelect => KeyCode::KEY_APPSELECT,
+            Key::ScreenSaver => KeyCode::KEY_SCREENSAVER,
+            Key::VoiceCommand => KeyCode::KEY_VOICECOMMAND,
+            Key::Assistant => KeyCode::KEY_ASSISTANT,
+            Key::KbdLayoutNext => KeyCode::KEY_KBD_LAYOUT_NEXT,
+            Key::EmojiPicker => KeyCode::new(0x249), // KEY_EMOJI_PICKER
+            Key::Dictate => KeyCode::new(0x24a),     // KEY_DICTATE
+            Key::BrightnessMin => KeyCode::KEY_BRIGHTNESS_MIN,
+            Key::BrightnessMax => KeyCode::KEY_BRIGHTNESS_MAX,
+            Key::KbdInputAssistPrev => KeyCode::KEY_KBDINPUTASSIST_PREV,
+            Key::KbdInputAssistNext => KeyCode::KEY_KBDINPUTASSIST_NEXT,
+            Key::KbdInputAssistPrevGroup => KeyCode::KEY_KBDINPUTASSIST_PREVGROUP,
+            Key::KbdInputAssistNextGroup => KeyCode::KEY_KBDINPUTASSIST_NEXTGROUP,
+            Key::KbdInputAssistAccept => KeyCode::KEY_KBDINPUTASSIST_ACCEPT,
+            Key::KbdInputAssistCancel => KeyCode::KEY_KBDINPUTASSIST_CANCEL,
+            Key::RightUp => KeyCode::KEY_RIGHT_UP,
+            Key::RightDown => KeyCode::KEY_RIGHT_DOWN,
+            Key::LeftUp => KeyCode::KEY_LEFT_UP,
+            Key::LeftDown => KeyCode::KEY_LEFT_DOWN,
+            Key::RootMenu => KeyCode::KEY_ROOT_MENU,
+            Key::MediaTopMenu => KeyCode::KEY_MEDIA_TOP_MENU,
+            Key::Numeric11 => KeyCode::KEY_NUMERIC_11,
+            Key::Numeric12 => KeyCode::KEY_NUMERIC_12,
+            Key::AudioDesc => KeyCode::KEY_AUDIO_DESC,
+            Key::ThreeDMode => KeyCode::KEY_3D_MODE,
+            Key::NextFavorite => KeyCode::KEY_NEXT_FAVORITE,
+            Key::StopRecord => KeyCode::KEY_STOP_RECORD,
+            Key::PauseRecord => KeyCode::KEY_PAUSE_RECORD,
+            Key::Vod => KeyCode::KEY_VOD,
+            Key::Unmute => KeyCode::KEY_UNMUTE,
+            Key::FastReverse => KeyCode::KEY_FASTREVERSE,
+            Key::SlowReverse => KeyCode::KEY_SLOWREVERSE,
+            Key::Data => KeyCode::KEY_DATA,
+            Key::OnscreenKeyboard => KeyCode::KEY_ONSCREEN_KEYBOARD,
+            Key::PrivacyScreenToggle => KeyCode::KEY_PRIVACY_SCREEN_TOGGLE,
+            Key::SelectiveScreenshot => KeyCode::KEY_SELECTIVE_SCREENSHOT,
+            Key::NextElement => KeyCode::new(0x27b), // KEY_NEXT_ELEMENT
+            Key::PreviousElement => KeyCode::new(0x27c), // KEY_PREVIOUS_ELEMENT
+            Key::AutopilotEngageToggle => KeyCode::new(0x27d), // KEY_AUTOPILOT_ENGAGE_TOGGLE
+            Key::MarkWaypoint => KeyCode::new(0x27e), // KEY_MARK_WAYPOINT
+            Key::Sos => KeyCode::new(0x27f),         // KEY_SOS
+            Key::NavChart => KeyCode::new(0x280),    // KEY_NAV_CHART
+            Key::FishingChart => KeyCode::new(0x281), // KEY_FISHING_CHART
+            Key::SingleRangeRadar => KeyCode::new(0x282), // KEY_SINGLE_RANGE_RADAR
+            Key::DualRangeRadar => KeyCode::new(0x283), // KEY_DUAL_RANGE_RADAR
+            Key::RadarOverlay => KeyCode::new(0x284), // KEY_RADAR_OVERLAY
+            Key::TraditionalSonar => KeyCode::new(0x285), // KEY_TRADITIONAL_SONAR
+            Key::ClearVuSonar => KeyCode::new(0x286), // KEY_CLEARVU_SONAR
+            Key::SideVuSonar => KeyCode::new(0x287), // KEY_SIDEVU_SONAR
+            Key::NavInfo => KeyCode::new(0x288),     // KEY_NAV_INFO
+            Key::BrightnessMenu => KeyCode::new(0x289), // KEY_BRIGHTNESS_MENU
+            Key::Macro1 => KeyCode::new(0x290),      // KEY_MACRO1
+            Key::Macro2 => KeyCode::new(0x291),      // KEY_MACRO2
+            Key::Macro3 => KeyCode::new(0x292),      // KEY_MACRO3
+            Key::Macro4 => KeyCode::new(0x293),      // KEY_MACRO4
+            Key::Macro5 => KeyCode::new(0x294),      // KEY_MACRO5
+            Key::Macro6 => KeyCode::new(0x295),      // KEY_MACRO6
+            Key::Macro7 => KeyCode::new(0x296),      // KEY_MACRO7
+            Key::Macro8 => KeyCode::new(0x297),      // KEY_MACRO8
+            Key::Macro9 => KeyCode::new(0x298),      // KEY_MACRO9
+            Key::Macro10 => KeyCode::new(0x299),     // KEY_MACRO10
+            Key::Macro11 => KeyCode::new(0x29a),     // KEY_MACRO11
+            Key::Macro12 => KeyCode::new(0x29b),     // KEY_MACRO12
+            Key::Macro13 => KeyCode::new(0x29c),     // KEY_MACRO13
+            Key::Macro14 => KeyCode::new(0x29d),     // KEY_MACRO14
+            Key::Macro15 => KeyCode::new(0x29e),     // KEY_MACRO15
+            Key::Macro16 => KeyCode::new(0x29f),     // KEY_MACRO16
+            Key::Macro17 => KeyCode::new(0x2a0),     // KEY_MACRO17
+            Key::Macro18 => KeyCode::new(0x2a1),     // KEY_MACRO18
+            Key::Macro19 => KeyCode::new(0x2a2),     // KEY_MACRO19
+            Key::Macro20 => KeyCode::new(0x2a3),     // KEY_MACRO20
+            Key::Macro21 => KeyCode::new(0x2a4),     // KEY_MACRO21
+            Key::Macro22 => KeyCode::new(0x2a5),     // KEY_MACRO22
+            Key::Macro23 => KeyCode::new(0x2a6),     // KEY_MACRO23
+            Key::Macro24 => KeyCode::new(0x2a7),     // KEY_MACRO24
+            Key::Macro25 => KeyCode::new(0x2a8),     // KEY_MACRO25
+            Key::Macro26 => KeyCode::new(0x2a9),     // KEY_MACRO26
+            Key::Macro27 => KeyCode::new(0x2aa),     // KEY_MACRO27
+            Key::Macro28 => KeyCode::new(0x2ab),     // KEY_MACRO28
+            Key::Macro29 => KeyCode::new(0x2ac),     // KEY_MACRO29
+            Key::Macro30 => KeyCode::new(0x2ad),     // KEY_MACRO30
+            Key::MacroRecordStart => KeyCode::new(0x2b0), // KEY_MACRO_RECORD_START
+            Key::MacroRecordStop => KeyCode::new(0x2b1), // KEY_MACRO_RECORD_STOP
+            Key::MacroPresetCycle => KeyCode::new(0x2b2), // KEY_MACRO_PRESET_CYCLE
+            Key::MacroPreset1 => KeyCode::new(0x2b3), // KEY_MACRO_PRESET1
+            Key::MacroPreset2 => KeyCode::new(0x2b4), // KEY_MACRO_PRESET2
+            Key::MacroPreset3 => KeyCode::new(0x2b5), // KEY_MACRO_PRESET3
+            Key::KbdLcdMenu1 => KeyCode::new(0x2b8), // KEY_KBD_LCD_MENU1
+            Key::KbdLcdMenu2 => KeyCode::new(0x2b9), // KEY_KBD_LCD_MENU2
+            Key::KbdLcdMenu3 => KeyCode::new(0x2ba), // KEY_KBD_LCD_MENU3
+            Key::KbdLcdMenu4 => KeyCode::new(0x2bb), // KEY_KBD_LCD_MENU4
+            Key::KbdLcdMenu5 => KeyCode::new(0x2bc), // KEY_KBD_LCD_MENU5
+            Key::Raw(code) => KeyCode::new(code),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A headless backend without any uinput devices, on a screen
+    /// that maps one pixel to 100 touchscreen units.
+    fn headless() -> PlatformImpl {
+        let size = (1000, 1000);
+        PlatformImpl {
+            conn: None,
+            geometry: Arc::new(RwLock::new(ScreenGeometry {
+                size,
+                monitors: vec![Monitor::whole_screen(size)],
+            })),
+            outputs: HashMap::new(),
+            rel_mouse_device: None,
+            abs_mouse_device: None,
+            keyboard_device: None,
+            touch_device: None,
+            pen_device: None,
+            touch_geometry: TouchscreenGeometry::default(),
+            pen_geometry: PenGeometry::default(),
+            pen_mode: PenMode::default(),
+            touches: TouchState::default(),
+            wheel_x: 0,
+            wheel_y: 0,
+            pen: None,
+            spare_keycode: None,
+        }
+    }
+
+    /// The frames written for a batch of operations.
+    fn frames(events: &[crate::InputEvent]) -> Vec<(UinputDevice, Vec<InputEvent>)> {
+        let mut platform = headless();
+        let mut frames = Vec::new();
+        platform
+            .send_frames(events, |_, device, events| {
+                frames.push((device, events.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+        frames
+    }
+
+    fn value(frame: &[InputEvent], event_type: EventType, code: u16) -> Option<i32> {
+        frame
+            .iter()
+            .rev()
+            .find(|event| event.event_type() == event_type && event.code() == code)
+            .map(|event| event.value())
+    }
+
+    fn down(slot: i32, x: i32, y: i32) -> crate::InputEvent {
+        crate::InputEvent::TouchDown {
+            slot,
+            x,
+            y,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    fn moved(slot: i32, x: i32, y: i32) -> crate::InputEvent {
+        crate::InputEvent::TouchMove {
+            slot,
+            x,
+            y,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    #[test]
+    fn rejects_device_names_evdev_cannot_hold() {
+        let identity = DeviceIdentity::new("a".repeat(79));
+        assert!(device_builder(&identity).is_err());
+    }
+
+    #[test]
+    fn keyboard_registers_no_buttons() {
+        let codes: Vec<_> = all_key_codes().collect();
+        assert!(codes.contains(&KeyCode::KEY_A));
+        assert!(codes.contains(&KeyCode::KEY_OK));
+        for button in [
+            KeyCode::BTN_LEFT,
+            KeyCode::BTN_TOUCH,
+            KeyCode::BTN_DPAD_UP,
+            KeyCode::BTN_DPAD_RIGHT,
+            KeyCode::BTN_TRIGGER_HAPPY1,
+        ] {
+            assert!(!codes.contains(&button), "{button:?}");
+        }
+    }
+
+    #[test]
+    fn mice_register_every_button() {
+        let codes: Vec<_> = mouse_button_codes().collect();
+        for button in MouseButton::iter().chain([MouseButton::Raw(KeyCode::BTN_0.code())]) {
+            assert!(codes.contains(&KeyCode::from(button)), "{button:?}");
+        }
+        assert!(!codes.contains(&KeyCode::BTN_TRIGGER));
+    }
+
+    #[test]
+    fn tap_reports_touch_in_its_own_frame() {
+        let frames = frames(&[down(0, 10, 20), crate::InputEvent::TouchUp { slot: 0 }]);
+        assert_eq!(frames.len(), 2);
+        let (device, press) = &frames[0];
+        assert_eq!(*device, UinputDevice::Touchscreen);
+        assert_eq!(value(press, EventType::KEY, KeyCode::BTN_TOUCH.0), Some(1));
+        assert_eq!(
+            value(press, EventType::KEY, KeyCode::BTN_TOOL_FINGER.0),
+            Some(1)
+        );
+        assert_eq!(
+            value(press, EventType::ABSOLUTE, AbsoluteAxisCode::ABS_X.0),
+            Some(1000)
+        );
+        assert_eq!(
+            value(press, EventType::ABSOLUTE, AbsoluteAxisCode::ABS_Y.0),
+            Some(2000)
+        );
+        let (_, release) = &frames[1];
+        assert_eq!(
+            value(release, EventType::KEY, KeyCode::BTN_TOUCH.0),
+            Some(0)
+        );
+        assert_eq!(
+            value(
+                release,
+                EventType::ABSOLUTE,
+                AbsoluteAxisCode::ABS_MT_TRACKING_ID.0
+            ),
+            Some(-1)
+        );
+    }
+
+    #[test]
+    fn drag_reports_each_position_in_its_frame() {
+        let frames = frames(&[
+            down(0, 10, 10),
+            moved(0, 20, 10),
+            moved(0, 30, 10),
+            crate::InputEvent::TouchUp { slot: 0 },
+        ]);
+        let positions: Vec<_> = frames
+            .iter()
+            .map(|(_, frame)| value(frame, EventType::ABSOLUTE, AbsoluteAxisCode::ABS_X.0))
+            .collect();
+        assert_eq!(positions, [Some(1000), Some(2000), Some(3000), None]);
+        let touching: Vec<_> = frames
+            .iter()
+            .map(|(_, frame)| value(frame, EventType::KEY, KeyCode::BTN_TOUCH.0))
+            .collect();
+        assert_eq!(touching, [Some(1), None, None, Some(0)]);
+    }
+
+    #[test]
+    fn rejected_contact_leaves_the_touch_state_unchanged() {
+        let mut platform = headless();
+        let result = platform.send_frames(&[down(0, 10, 10), down(99, 20, 20)], |_, _, _| {
+            panic!("a rejected frame must not be written")
+        });
+        assert!(matches!(result, Err(SimulationError::InvalidSlot(99))));
+        assert!(platform.touches.frame_tail().is_empty());
+
+        let mut frames = Vec::new();
+        platform
+            .send_frames(&[down(1, 10, 10)], |_, _, events| {
+                frames.push(events.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        let frame = &frames[0];
+        assert_eq!(
+            value(frame, EventType::KEY, KeyCode::BTN_TOOL_FINGER.0),
+            Some(1)
+        );
+        assert_eq!(
+            value(
+                frame,
+                EventType::ABSOLUTE,
+                AbsoluteAxisCode::ABS_MT_TRACKING_ID.0
+            ),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn unwritten_frame_is_rolled_back() {
+        let mut platform = headless();
+        let result = platform.send_frames(&[down(0, 10, 10)], |_, _, _| {
+            Err(SimulationError::DeviceDisabled(DeviceClass::Touch))
+        });
+        assert!(result.is_err());
+        assert!(platform.touches.frame_tail().is_empty());
+    }
+
+    #[test]
+    fn contacts_in_one_frame_share_the_tail() {
+        let frames = frames(&[down(0, 10, 10), down(1, 20, 20)]);
+        assert_eq!(frames.len(), 1);
+        let (_, frame) = &frames[0];
+        assert_eq!(
+            value(frame, EventType::KEY, KeyCode::BTN_TOOL_DOUBLETAP.0),
+            Some(1)
+        );
+        assert_eq!(
+            value(frame, EventType::KEY, KeyCode::BTN_TOOL_FINGER.0),
+            None
+        );
+        assert_eq!(
+            value(frame, EventType::ABSOLUTE, AbsoluteAxisCode::ABS_X.0),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn tablet_mapping_covers_the_area() {
+        let geometry = PenGeometry::default();
+        let size = (1920, 1080);
+        assert_eq!(
+            TabletMapping::default().window(&geometry, size),
+            [0.0, 0.0, 100_000.0, 100_000.0]
+        );
+        assert_eq!(
+            TabletMapping::default()
+                .with_area(0.25, 0.0, 0.75, 1.0)
+                .window(&geometry, size),
+            [25_000.0, 0.0, 75_000.0, 100_000.0]
+        );
+    }
+
+    #[test]
+    fn tablet_mapping_keeps_the_physical_aspect_ratio() {
+        let mapping = TabletMapping::default().with_keep_aspect_ratio(true);
+        let square = PenGeometry::default();
+        assert_eq!(
+            mapping.window(&square, (1920, 1080)),
+            [0.0, 0.0, 100_000.0, 56_250.0]
+        );
+        // Twice as many units per millimetre along X: the tablet is
+        // physically square.
+        let dense = PenGeometry {
+            max_y: 50_000,
+            resolution_y: 5,
+            ..PenGeometry::default()
+        };
+        assert_eq!(
+            mapping.window(&dense, (2000, 1000)),
+            [0.0, 0.0, 100_000.0, 25_000.0]
+        );
+    }
+
+    #[test]
+    fn opaque_pen_scales_into_the_mapped_area() {
+        let mut platform = headless();
+        platform.pen_mode = PenMode::Opaque(TabletMapping::default().with_area(0.5, 0.5, 1.0, 1.0));
+        assert_eq!(
+            platform
+                .scale_to_device(UinputDevice::Pen, 500, 500)
+                .unwrap(),
+            (75_000, 75_000)
+        );
+    }
+}
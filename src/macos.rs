@@ -3,13 +3,16 @@ use core_graphics::event::*;
 use core_graphics::event_source::*;
 use core_graphics::geometry::CGPoint;
 
+use std::collections::HashSet;
 use std::time::{Duration, Instant};
 
-use crate::Key;
+use crate::backend::InputBackend;
+use crate::{DeviceClass, InputSimulatorBuilder, Key, MouseButton, PenState, TouchAttributes};
 use thiserror::Error;
 
 extern "C" {
     fn AXIsProcessTrusted() -> bool;
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: CGMouseButton) -> bool;
 }
 
 fn has_permission() -> bool {
@@ -22,6 +25,29 @@ pub enum SimulationError {
     CoreGraphicsError,
     #[error("The application does not have the requisite accessibility permissions to perform input simulation")]
     PermissionError,
+    #[error("The keyboard layout cannot produce these characters: {0:?}")]
+    UnsupportedCharacters(Vec<char>),
+    #[error("The {0:?} device class was not enabled when the simulator was built")]
+    DeviceDisabled(DeviceClass),
+    #[error("The {0:?} mouse button is not supported on this platform")]
+    UnsupportedButton(MouseButton),
+    #[error("Failed to install the signal handler: {0}")]
+    SignalHandler(String),
+    #[error("Backend error: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl SimulationError {
+    /// Whether the operation was refused before anything reached the
+    /// host, so that it cannot have partially taken effect.
+    pub(crate) fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            SimulationError::UnsupportedCharacters(_)
+                | SimulationError::DeviceDisabled(_)
+                | SimulationError::UnsupportedButton(_)
+        )
+    }
 }
 
 pub(crate) struct PlatformImpl {
@@ -35,10 +61,17 @@ pub(crate) struct PlatformImpl {
     last_right_click: Instant,
 
     flags: CGEventFlags,
+
+    devices: HashSet<DeviceClass>,
 }
 
+// SAFETY: Core Graphics event sources and displays can be used from
+// any thread, and the simulator only ever uses them from one thread
+// at a time.
+unsafe impl Send for PlatformImpl {}
+
 impl PlatformImpl {
-    pub(crate) fn new() -> Result<Self, SimulationError> {
+    pub(crate) fn new(builder: &InputSimulatorBuilder) -> Result<Self, SimulationError> {
         if !has_permission() {
             return Err(SimulationError::PermissionError);
         }
@@ -53,17 +86,29 @@ impl PlatformImpl {
             last_left_click: Instant::now(),
             last_right_click: Instant::now(),
             flags: CGEventFlags::CGEventFlagNull,
+            devices: builder.devices.clone(),
         })
     }
 
+    fn require(&self, device: DeviceClass) -> Result<(), SimulationError> {
+        if self.devices.contains(&device) {
+            Ok(())
+        } else {
+            Err(SimulationError::DeviceDisabled(device))
+        }
+    }
+
     fn show_cursor(&self) -> Result<(), SimulationError> {
         self.display
             .show_cursor()
             .map_err(|_| SimulationError::CoreGraphicsError)?;
         Ok(())
     }
+}
 
-    pub(crate) fn move_mouse_abs(&self, x: i32, y: i32) -> Result<(), SimulationError> {
+impl InputBackend for PlatformImpl {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let event_type = if self.left_mouse_down {
             CGEventType::LeftMouseDragged
         } else if self.right_mouse_down {
@@ -85,7 +130,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn move_mouse_rel(&self, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let event_type = if self.left_mouse_down {
             CGEventType::LeftMouseDragged
         } else if self.right_mouse_down {
@@ -114,7 +160,141 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn left_mouse_down(&mut self) -> Result<(), SimulationError> {
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        match button {
+            MouseButton::Left => self.left_mouse_down(),
+            MouseButton::Right => self.right_mouse_down(),
+            _ => self.other_mouse_button(button, true),
+        }
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        match button {
+            MouseButton::Left => self.left_mouse_up(),
+            MouseButton::Right => self.right_mouse_up(),
+            _ => self.other_mouse_button(button, false),
+        }
+    }
+
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
+        let event =
+            CGEvent::new_scroll_event(self.source.clone(), ScrollEventUnit::PIXEL, 2, y, x, 0)
+                .map_err(|_| SimulationError::CoreGraphicsError)?;
+        event.post(CGEventTapLocation::Session);
+        Ok(())
+    }
+
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
+        Ok((
+            self.display.pixels_wide() as _,
+            self.display.pixels_high() as _,
+        ))
+    }
+
+    fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        let event =
+            CGEvent::new(self.source.clone()).map_err(|_| SimulationError::CoreGraphicsError)?;
+        let loc = event.location();
+        Ok(Some((loc.x as i32, loc.y as i32)))
+    }
+
+    fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        let buttons = [
+            (CGMouseButton::Left, MouseButton::Left),
+            (CGMouseButton::Center, MouseButton::Middle),
+            (CGMouseButton::Right, MouseButton::Right),
+        ];
+        Ok(Some(
+            buttons
+                .into_iter()
+                .filter(|&(cg_button, _)| unsafe {
+                    CGEventSourceButtonState(CGEventSourceStateID::CombinedSessionState, cg_button)
+                })
+                .map(|(_, button)| button)
+                .collect(),
+        ))
+    }
+
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Keyboard)?;
+        if let Some(keycode) = key_to_cgkeycode(key) {
+            // Update flags
+            match key {
+                Key::CapsLock => self.flags ^= CGEventFlags::CGEventFlagAlphaShift,
+                Key::LeftShift | Key::RightShift => self.flags |= CGEventFlags::CGEventFlagShift,
+                Key::LeftCtrl | Key::RightCtrl => self.flags |= CGEventFlags::CGEventFlagControl,
+                Key::LeftAlt | Key::RightAlt => self.flags |= CGEventFlags::CGEventFlagAlternate,
+                Key::LeftMeta | Key::RightMeta => self.flags |= CGEventFlags::CGEventFlagCommand,
+                _ => {}
+            }
+            let event = CGEvent::new_keyboard_event(self.source.clone(), keycode, true)
+                .map_err(|_| SimulationError::CoreGraphicsError)?;
+            event.set_flags(self.flags);
+            event.post(CGEventTapLocation::HID);
+        }
+        Ok(())
+    }
+
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Keyboard)?;
+        if let Some(keycode) = key_to_cgkeycode(key) {
+            match key {
+                Key::LeftShift | Key::RightShift => self.flags &= !CGEventFlags::CGEventFlagShift,
+                Key::LeftCtrl | Key::RightCtrl => self.flags &= !CGEventFlags::CGEventFlagControl,
+                Key::LeftAlt | Key::RightAlt => self.flags &= !CGEventFlags::CGEventFlagAlternate,
+                Key::LeftMeta | Key::RightMeta => self.flags &= !CGEventFlags::CGEventFlagCommand,
+                _ => {}
+            }
+            let event = CGEvent::new_keyboard_event(self.source.clone(), keycode, false)
+                .map_err(|_| SimulationError::CoreGraphicsError)?;
+            event.set_flags(self.flags);
+            event.post(CGEventTapLocation::HID);
+            self.show_cursor()?;
+        }
+        Ok(())
+    }
+
+    fn touch_down(
+        &mut self,
+        _slot: i32,
+        _x: i32,
+        _y: i32,
+        _attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
+        // TODO: no-op
+        Ok(())
+    }
+
+    fn touch_up(&mut self, _slot: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
+        // TODO: no-op
+        Ok(())
+    }
+
+    fn touch_move(
+        &mut self,
+        _slot: i32,
+        _x: i32,
+        _y: i32,
+        _attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
+        // TODO: no-op
+        Ok(())
+    }
+
+    fn pen(&mut self, _state: PenState) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Pen)?;
+        // TODO: no-op
+        Ok(())
+    }
+}
+
+impl PlatformImpl {
+    fn left_mouse_down(&mut self) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let now = Instant::now();
         let is_double_click = (now - self.last_left_click) < Duration::from_millis(500);
         self.left_mouse_down = true;
@@ -138,12 +318,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn middle_mouse_down(&self) -> Result<(), SimulationError> {
-        // TODO: no-op
-        Ok(())
-    }
-
-    pub(crate) fn right_mouse_down(&mut self) -> Result<(), SimulationError> {
+    fn right_mouse_down(&mut self) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let now = Instant::now();
         let is_double_click = (now - self.last_right_click) < Duration::from_millis(500);
         self.right_mouse_down = true;
@@ -167,7 +343,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn left_mouse_up(&mut self) -> Result<(), SimulationError> {
+    fn left_mouse_up(&mut self) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let now = Instant::now();
         let is_double_click = (now - self.last_left_click) < Duration::from_millis(500);
         self.last_left_click = now;
@@ -192,12 +369,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn middle_mouse_up(&self) -> Result<(), SimulationError> {
-        // TODO: no-op
-        Ok(())
-    }
-
-    pub(crate) fn right_mouse_up(&mut self) -> Result<(), SimulationError> {
+    fn right_mouse_up(&mut self) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let now = Instant::now();
         let is_double_click = (now - self.last_right_click) < Duration::from_millis(500);
         self.last_right_click = now;
@@ -222,92 +395,43 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn wheel(&self, x: i32, y: i32) -> Result<(), SimulationError> {
-        let event =
-            CGEvent::new_scroll_event(self.source.clone(), ScrollEventUnit::PIXEL, 2, y, x, 0)
-                .map_err(|_| SimulationError::CoreGraphicsError)?;
-        event.post(CGEventTapLocation::Session);
-        Ok(())
-    }
-
-    pub(crate) fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
-        Ok((
-            self.display.pixels_wide() as _,
-            self.display.pixels_high() as _,
-        ))
-    }
-
-    pub(crate) fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
-        if let Some(keycode) = key_to_cgkeycode(key) {
-            // Update flags
-            match key {
-                Key::CapsLock => self.flags ^= CGEventFlags::CGEventFlagAlphaShift,
-                Key::LeftShift | Key::RightShift => self.flags |= CGEventFlags::CGEventFlagShift,
-                Key::LeftCtrl | Key::RightCtrl => self.flags |= CGEventFlags::CGEventFlagControl,
-                Key::LeftAlt | Key::RightAlt => self.flags |= CGEventFlags::CGEventFlagAlternate,
-                Key::LeftMeta | Key::RightMeta => self.flags |= CGEventFlags::CGEventFlagCommand,
-                _ => {}
-            }
-            let event = CGEvent::new_keyboard_event(self.source.clone(), keycode, true)
-                .map_err(|_| SimulationError::CoreGraphicsError)?;
-            event.set_flags(self.flags);
-            event.post(CGEventTapLocation::HID);
-        }
-        Ok(())
-    }
-
-    pub(crate) fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
-        if let Some(keycode) = key_to_cgkeycode(key) {
-            match key {
-                Key::LeftShift | Key::RightShift => self.flags &= !CGEventFlags::CGEventFlagShift,
-                Key::LeftCtrl | Key::RightCtrl => self.flags &= !CGEventFlags::CGEventFlagControl,
-                Key::LeftAlt | Key::RightAlt => self.flags &= !CGEventFlags::CGEventFlagAlternate,
-                Key::LeftMeta | Key::RightMeta => self.flags &= !CGEventFlags::CGEventFlagCommand,
-                _ => {}
-            }
-            let event = CGEvent::new_keyboard_event(self.source.clone(), keycode, false)
-                .map_err(|_| SimulationError::CoreGraphicsError)?;
-            event.set_flags(self.flags);
-            event.post(CGEventTapLocation::HID);
-            self.show_cursor()?;
-        }
-        Ok(())
-    }
-
-    pub(crate) fn touch_down(
-        &mut self,
-        _slot: i32,
-        _x: i32,
-        _y: i32,
-    ) -> Result<(), SimulationError> {
-        // TODO: no-op
-        Ok(())
-    }
-
-    pub(crate) fn touch_up(&mut self, _slot: i32) -> Result<(), SimulationError> {
-        // TODO: no-op
-        Ok(())
-    }
-
-    pub(crate) fn touch_move(
+    /// Press or release a button other than the left and right ones.
+    /// macOS identifies these buttons by number.
+    fn other_mouse_button(
         &mut self,
-        _slot: i32,
-        _x: i32,
-        _y: i32,
+        button: MouseButton,
+        down: bool,
     ) -> Result<(), SimulationError> {
-        // TODO: no-op
-        Ok(())
-    }
+        self.require(DeviceClass::Mouse)?;
+        let number = match button {
+            MouseButton::Left => 0,
+            MouseButton::Right => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Side => 3,
+            MouseButton::Extra => 4,
+            MouseButton::Forward => 5,
+            MouseButton::Back => 6,
+            MouseButton::Task => 7,
+            MouseButton::Raw(_) => return Err(SimulationError::UnsupportedButton(button)),
+        };
+        // Get mouse position
+        let event =
+            CGEvent::new(self.source.clone()).map_err(|_| SimulationError::CoreGraphicsError)?;
+        let loc = event.location();
+        let event = CGEvent::new_mouse_event(
+            self.source.clone(),
+            if down {
+                CGEventType::OtherMouseDown
+            } else {
+                CGEventType::OtherMouseUp
+            },
+            loc,
+            CGMouseButton::Center,
+        )
+        .map_err(|_| SimulationError::CoreGraphicsError)?;
+        event.set_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER, number);
+        event.post(CGEventTapLocation::Session);
 
-    pub(crate) fn pen(
-        &mut self,
-        _x: i32,
-        _y: i32,
-        _pressure: f64,
-        _tilt_x: i32,
-        _tilt_y: i32,
-    ) -> Result<(), SimulationError> {
-        // TODO: no-op
         Ok(())
     }
 }
@@ -572,6 +696,7 @@ fn key_to_cgkeycode(key: Key) -> Option<CGKeyCode> {
         | Key::FnEsc
         | Key::KpComma => return None,
 
+        Key::Raw(code) => code,
         _ => return None, // Unimplemented / unsupported on macOS
     })
 }
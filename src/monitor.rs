@@ -0,0 +1,55 @@
+/// How the contents of a monitor are rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    Normal,
+    /// Rotated 90 degrees counter-clockwise.
+    Left,
+    Inverted,
+    /// Rotated 90 degrees clockwise.
+    Right,
+}
+
+/// A monitor, described by the area it covers in the virtual screen.
+///
+/// Returned by [InputSimulator::monitors](crate::InputSimulator::monitors).
+/// The position and size are in pixels of the virtual screen, after
+/// rotation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Monitor {
+    /// The name of the output driving the monitor, such as `HDMI-1`.
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub rotation: Rotation,
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// A monitor covering the whole virtual screen, for backends
+    /// that cannot tell monitors apart.
+    pub(crate) fn whole_screen((width, height): (i32, i32)) -> Self {
+        Self {
+            name: "screen".to_string(),
+            x: 0,
+            y: 0,
+            width,
+            height,
+            rotation: Rotation::Normal,
+            primary: true,
+        }
+    }
+
+    /// Convert a point relative to the top left corner of the monitor
+    /// to virtual screen coordinates.
+    pub fn to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        (self.x + x, self.y + y)
+    }
+
+    /// Whether a point in virtual screen coordinates is on the monitor.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The shape and timing of a smooth pointer motion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Path {
+    /// A straight line at constant speed.
+    Linear,
+    /// A straight line that speeds up and slows down at the ends.
+    Eased,
+    /// An eased curve that bows to one side of the straight line,
+    /// with small random deviations along the way.
+    Curved {
+        /// How far the curve bows out, as a fraction of the distance
+        /// travelled. `0.2` gives a gentle arc.
+        curvature: f64,
+        /// The largest random deviation from the curve, in pixels.
+        noise: f64,
+    },
+}
+
+impl Path {
+    /// The positions visited when moving from `from` to `to` in
+    /// `steps` steps, ending exactly at `to`.
+    pub(crate) fn points(self, from: (i32, i32), to: (i32, i32), steps: u32) -> Vec<(i32, i32)> {
+        let (x0, y0) = (from.0 as f64, from.1 as f64);
+        let (x1, y1) = (to.0 as f64, to.1 as f64);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let mut rng = Rng::new();
+
+        // For curves, bow out perpendicular to the line, to a random
+        // side, through two control points of a cubic Bézier curve.
+        let (curvature, noise) = match self {
+            Path::Curved { curvature, noise } => (curvature, noise),
+            _ => (0.0, 0.0),
+        };
+        let side = if rng.next_f64() < 0.5 { -1.0 } else { 1.0 };
+        let (nx, ny) = (-dy * curvature * side, dx * curvature * side);
+        let c1 = (x0 + dx / 3.0 + nx, y0 + dy / 3.0 + ny);
+        let c2 = (x0 + dx * 2.0 / 3.0 + nx, y0 + dy * 2.0 / 3.0 + ny);
+
+        (1..=steps)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                if i == steps {
+                    return to;
+                }
+                let t = match self {
+                    Path::Linear => t,
+                    Path::Eased | Path::Curved { .. } => ease_in_out(t),
+                };
+                let (x, y) = bezier((x0, y0), c1, c2, (x1, y1), t);
+                // Taper the noise towards the ends of the path.
+                let amplitude = noise * (std::f64::consts::PI * t).sin();
+                let x = x + (rng.next_f64() * 2.0 - 1.0) * amplitude;
+                let y = y + (rng.next_f64() * 2.0 - 1.0) * amplitude;
+                (x.round() as i32, y.round() as i32)
+            })
+            .collect()
+    }
+}
+
+/// The number of steps a motion of the given duration takes at
+/// `rate` updates per second.
+pub(crate) fn steps(duration: Duration, rate: u32) -> u32 {
+    ((duration.as_secs_f64() * rate as f64).round() as u32).max(1)
+}
+
+fn ease_in_out(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn bezier(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), t: f64) -> (f64, f64) {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    (
+        a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+        a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+    )
+}
+
+/// A small xorshift generator. The noise only has to look irregular,
+/// so it is seeded from the clock.
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self(seed | 1)
+    }
+
+    /// A number in `0.0..1.0`.
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATHS: [Path; 3] = [
+        Path::Linear,
+        Path::Eased,
+        Path::Curved {
+            curvature: 0.2,
+            noise: 3.0,
+        },
+    ];
+
+    #[test]
+    fn paths_end_exactly_at_the_target() {
+        for path in PATHS {
+            let points = path.points((10, 20), (310, -80), 30);
+            assert_eq!(points.len(), 30);
+            assert_eq!(points.last(), Some(&(310, -80)), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn single_step_jumps_to_the_target() {
+        for path in PATHS {
+            assert_eq!(path.points((0, 0), (5, 5), 1), [(5, 5)]);
+        }
+    }
+
+    #[test]
+    fn linear_path_moves_at_constant_speed() {
+        let points = Path::Linear.points((0, 0), (100, 0), 4);
+        assert_eq!(points, [(25, 0), (50, 0), (75, 0), (100, 0)]);
+    }
+
+    #[test]
+    fn steps_follow_the_rate() {
+        assert_eq!(steps(Duration::from_millis(500), 120), 60);
+        assert_eq!(steps(Duration::ZERO, 120), 1);
+    }
+}
@@ -0,0 +1,99 @@
+/// The end of the pen that is in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PenTool {
+    #[default]
+    Pen,
+    Eraser,
+}
+
+/// The complete state of the pen, passed to
+/// [InputSimulator::pen](crate::InputSimulator::pen).
+///
+/// A pen can be out of range, hovering in range of the surface, or
+/// in contact with it. The default state is out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PenState {
+    pub x: i32,
+    pub y: i32,
+    /// Whether the pen is close enough to the surface to be detected.
+    pub in_range: bool,
+    /// Whether the pen touches the surface. Ignored when the pen is
+    /// out of range.
+    pub contact: bool,
+    /// The pressure on the surface, from 0.0 to 1.0. Ignored without
+    /// contact.
+    pub pressure: f64,
+    /// The height above the surface while hovering, from 0.0 to 1.0.
+    /// Ignored while in contact.
+    pub distance: f64,
+    /// The tilt along the X axis, in degrees between -90 and 90.
+    pub tilt_x: i32,
+    /// The tilt along the Y axis, in degrees between -90 and 90.
+    pub tilt_y: i32,
+    /// The clockwise rotation of the pen around its own axis, in
+    /// degrees.
+    pub rotation: i32,
+    /// Whether the first barrel button is pressed.
+    pub barrel: bool,
+    /// Whether the second barrel button is pressed.
+    pub barrel2: bool,
+    pub tool: PenTool,
+}
+
+impl PenState {
+    /// A pen hovering in range over a point, without touching it.
+    pub fn hover(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            in_range: true,
+            ..Self::default()
+        }
+    }
+
+    /// A pen pressing on a point.
+    pub fn contact(x: i32, y: i32, pressure: f64) -> Self {
+        Self {
+            contact: true,
+            pressure,
+            ..Self::hover(x, y)
+        }
+    }
+
+    /// A pen that has left the range of the surface above a point.
+    pub fn out_of_range(x: i32, y: i32) -> Self {
+        Self {
+            x,
+            y,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_distance(mut self, distance: f64) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_tilt(mut self, tilt_x: i32, tilt_y: i32) -> Self {
+        self.tilt_x = tilt_x;
+        self.tilt_y = tilt_y;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: i32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set which barrel buttons are pressed.
+    pub fn with_barrel_buttons(mut self, barrel: bool, barrel2: bool) -> Self {
+        self.barrel = barrel;
+        self.barrel2 = barrel2;
+        self
+    }
+
+    pub fn with_tool(mut self, tool: PenTool) -> Self {
+        self.tool = tool;
+        self
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::backend::InputBackend;
+use crate::{InputEvent, Key, MouseButton, PenState, SimulationError, TouchAttributes};
+
+/// An [InputEvent] captured by a recording simulator, along with
+/// the time at which it was received.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedEvent {
+    /// Time elapsed between the creation of the log and the event.
+    pub time: Duration,
+    pub event: InputEvent,
+}
+
+/// A shared handle to the events captured by a simulator created
+/// with [InputSimulator::recording](crate::InputSimulator::recording).
+///
+/// Cloning the handle does not copy the log; every clone observes
+/// the same events.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    start: Instant,
+    entries: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl EventLog {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn push(&self, event: InputEvent) {
+        let time = self.start.elapsed();
+        self.entries
+            .lock()
+            .unwrap()
+            .push(RecordedEvent { time, event });
+    }
+
+    /// All events recorded so far, with their timestamps.
+    pub fn entries(&self) -> Vec<RecordedEvent> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    /// All events recorded so far, without timestamps.
+    pub fn events(&self) -> Vec<InputEvent> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.event)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Discard every event recorded so far.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// A backend that accepts every operation and appends it to an
+/// [EventLog] instead of touching any real device.
+pub(crate) struct RecordingBackend {
+    log: EventLog,
+}
+
+impl RecordingBackend {
+    /// The screen size reported by a recording backend.
+    pub(crate) const SCREEN_SIZE: (i32, i32) = (1920, 1080);
+
+    pub(crate) fn new() -> (Self, EventLog) {
+        let log = EventLog::new();
+        (Self { log: log.clone() }, log)
+    }
+
+    fn record(&mut self, event: InputEvent) -> Result<(), SimulationError> {
+        self.log.push(event);
+        Ok(())
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.record(InputEvent::MoveMouseAbs { x, y })
+    }
+
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.record(InputEvent::MoveMouseRel { x, y })
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.record(InputEvent::MouseDown(button))
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.record(InputEvent::MouseUp(button))
+    }
+
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.record(InputEvent::Wheel { x, y })
+    }
+
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.record(InputEvent::KeyDown(key))
+    }
+
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.record(InputEvent::KeyUp(key))
+    }
+
+    fn touch_down(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.record(InputEvent::TouchDown {
+            slot,
+            x,
+            y,
+            attributes,
+        })
+    }
+
+    fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
+        self.record(InputEvent::TouchUp { slot })
+    }
+
+    fn touch_move(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.record(InputEvent::TouchMove {
+            slot,
+            x,
+            y,
+            attributes,
+        })
+    }
+
+    fn pen(&mut self, state: PenState) -> Result<(), SimulationError> {
+        self.record(InputEvent::Pen(state))
+    }
+
+    fn type_unicode(&mut self, c: char) -> Result<(), SimulationError> {
+        self.record(InputEvent::TypeUnicode(c))
+    }
+
+    fn can_type_unicode(&self) -> bool {
+        true
+    }
+
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
+        Ok(Self::SCREEN_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InputEvent, InputSimulator, Key, MouseButton};
+
+    #[test]
+    fn records_operations_in_order() {
+        let (mut simulator, log) = InputSimulator::recording();
+        simulator.move_mouse_abs(10, 20).unwrap();
+        simulator.left_mouse_down().unwrap();
+        simulator.left_mouse_up().unwrap();
+        simulator.wheel(0, 120).unwrap();
+        assert_eq!(
+            log.events(),
+            [
+                InputEvent::MoveMouseAbs { x: 10, y: 20 },
+                InputEvent::MouseDown(MouseButton::Left),
+                InputEvent::MouseUp(MouseButton::Left),
+                InputEvent::Wheel { x: 0, y: 120 },
+            ]
+        );
+        let entries = log.entries();
+        assert!(entries.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn clones_share_the_log() {
+        let (mut simulator, log) = InputSimulator::recording();
+        let clone = log.clone();
+        simulator.key_down(Key::A).unwrap();
+        assert_eq!(clone.len(), 1);
+        clone.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn reports_a_fixed_screen_size() {
+        let (simulator, _log) = InputSimulator::recording();
+        assert_eq!(simulator.get_screen_size().unwrap(), (1920, 1080));
+        assert_eq!(simulator.mouse_position().unwrap(), None);
+    }
+
+    #[test]
+    fn releases_held_input_on_drop() {
+        let (mut simulator, log) = InputSimulator::recording();
+        simulator.key_down(Key::LeftShift).unwrap();
+        log.clear();
+        drop(simulator);
+        assert_eq!(log.events(), [InputEvent::KeyUp(Key::LeftShift)]);
+    }
+
+    #[test]
+    fn types_characters_outside_the_layout_as_unicode() {
+        let (mut simulator, log) = InputSimulator::recording();
+        simulator.type_text("a€").unwrap();
+        assert_eq!(
+            log.events(),
+            [
+                InputEvent::KeyDown(Key::A),
+                InputEvent::KeyUp(Key::A),
+                InputEvent::TypeUnicode('€'),
+            ]
+        );
+    }
+}
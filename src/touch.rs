@@ -0,0 +1,173 @@
+use crate::InputEvent;
+
+/// What happens to a touch contact in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchPhase {
+    /// The contact touches the screen.
+    Down,
+    /// The contact moves while touching the screen.
+    Move,
+    /// The contact is lifted. Its position is ignored.
+    Up,
+}
+
+/// What is touching the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchTool {
+    Finger,
+    Palm,
+    Pen,
+}
+
+/// Optional properties of a touch contact.
+///
+/// Attributes left unset when a contact touches down take their
+/// defaults: half [pressure](TouchAttributes::pressure), a round
+/// contact 12 pixels across, no rotation and a
+/// [finger](TouchTool::Finger). Attributes left unset when a contact
+/// moves keep their previous values.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TouchAttributes {
+    /// The pressure of the contact, from 0.0 to 1.0.
+    pub pressure: Option<f64>,
+    /// The length of the major axis of the contact area, in pixels.
+    pub touch_major: Option<i32>,
+    /// The length of the minor axis of the contact area, in pixels.
+    pub touch_minor: Option<i32>,
+    /// The angle of the major axis, in degrees clockwise from the
+    /// vertical, between -90 and 90.
+    pub orientation: Option<i32>,
+    pub tool: Option<TouchTool>,
+}
+
+impl TouchAttributes {
+    pub(crate) const DEFAULT_PRESSURE: f64 = 0.5;
+    pub(crate) const DEFAULT_SIZE: i32 = 12;
+    pub(crate) const DEFAULT_ORIENTATION: i32 = 0;
+    pub(crate) const DEFAULT_TOOL: TouchTool = TouchTool::Finger;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pressure(mut self, pressure: f64) -> Self {
+        self.pressure = Some(pressure);
+        self
+    }
+
+    /// Set the major and minor axes of the contact area, in pixels.
+    pub fn with_size(mut self, major: i32, minor: i32) -> Self {
+        self.touch_major = Some(major);
+        self.touch_minor = Some(minor);
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: i32) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: TouchTool) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    /// These attributes, with the ones that are not set taken from
+    /// `previous`.
+    pub(crate) fn or(self, previous: Self) -> Self {
+        Self {
+            pressure: self.pressure.or(previous.pressure),
+            touch_major: self.touch_major.or(previous.touch_major),
+            touch_minor: self.touch_minor.or(previous.touch_minor),
+            orientation: self.orientation.or(previous.orientation),
+            tool: self.tool.or(previous.tool),
+        }
+    }
+
+    /// Fill in the attributes that are not set with the defaults used
+    /// when a contact touches down.
+    pub(crate) fn or_defaults(self) -> Self {
+        self.or(Self {
+            pressure: Some(Self::DEFAULT_PRESSURE),
+            touch_major: Some(Self::DEFAULT_SIZE),
+            touch_minor: Some(Self::DEFAULT_SIZE),
+            orientation: Some(Self::DEFAULT_ORIENTATION),
+            tool: Some(Self::DEFAULT_TOOL),
+        })
+    }
+}
+
+/// The state of one touch slot in a frame passed to
+/// [InputSimulator::touch_frame](crate::InputSimulator::touch_frame).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchContact {
+    pub slot: i32,
+    pub x: i32,
+    pub y: i32,
+    pub phase: TouchPhase,
+    /// Ignored when the contact is lifted.
+    pub attributes: TouchAttributes,
+}
+
+impl TouchContact {
+    pub fn down(slot: i32, x: i32, y: i32) -> Self {
+        Self {
+            slot,
+            x,
+            y,
+            phase: TouchPhase::Down,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    pub fn moved(slot: i32, x: i32, y: i32) -> Self {
+        Self {
+            slot,
+            x,
+            y,
+            phase: TouchPhase::Move,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    pub fn up(slot: i32) -> Self {
+        Self {
+            slot,
+            x: 0,
+            y: 0,
+            phase: TouchPhase::Up,
+            attributes: TouchAttributes::default(),
+        }
+    }
+
+    pub fn with_attributes(mut self, attributes: TouchAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// The operation that performs this update on its own.
+    pub(crate) fn event(&self) -> InputEvent {
+        let Self {
+            slot,
+            x,
+            y,
+            attributes,
+            ..
+        } = *self;
+        match self.phase {
+            TouchPhase::Down => InputEvent::TouchDown {
+                slot,
+                x,
+                y,
+                attributes,
+            },
+            TouchPhase::Move => InputEvent::TouchMove {
+                slot,
+                x,
+                y,
+                attributes,
+            },
+            TouchPhase::Up => InputEvent::TouchUp { slot },
+        }
+    }
+}
@@ -1,6 +1,12 @@
-use crate::Key;
+use crate::backend::InputBackend;
+use crate::{
+    DeviceClass, InputSimulatorBuilder, Key, Monitor, MouseButton, PenState, PenTool,
+    TouchAttributes,
+};
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
+use windows::Win32::Foundation;
 use windows::Win32::UI::Controls;
 use windows::Win32::UI::HiDpi;
 use windows::Win32::UI::Input::KeyboardAndMouse;
@@ -11,12 +17,40 @@ use windows::Win32::UI::WindowsAndMessaging;
 pub enum SimulationError {
     #[error("Windows error: {0}")]
     WindowsError(#[from] windows::core::Error),
+    #[error("The keyboard layout cannot produce these characters: {0:?}")]
+    UnsupportedCharacters(Vec<char>),
+    #[error("The {0:?} device class was not enabled when the simulator was built")]
+    DeviceDisabled(DeviceClass),
+    #[error("The {0:?} mouse button is not supported on this platform")]
+    UnsupportedButton(MouseButton),
+    #[error("The touchscreen has no slot {0}")]
+    InvalidSlot(i32),
+    #[error("Failed to install the signal handler: {0}")]
+    SignalHandler(String),
+    #[error("Backend error: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl SimulationError {
+    /// Whether the operation was refused before anything reached the
+    /// host, so that it cannot have partially taken effect.
+    pub(crate) fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            SimulationError::UnsupportedCharacters(_)
+                | SimulationError::DeviceDisabled(_)
+                | SimulationError::UnsupportedButton(_)
+                | SimulationError::InvalidSlot(_)
+        )
+    }
 }
 
 #[derive(Clone, Copy)]
 struct Touch {
     x: i32,
     y: i32,
+    /// The contact attributes, all of which are set.
+    attributes: TouchAttributes,
     active: bool,
     /// If set, the next tick will emit this exact transition flag once.
     /// After emission, it's cleared (and `active` set false if it was UP).
@@ -28,6 +62,7 @@ impl Default for Touch {
         Self {
             x: 0,
             y: 0,
+            attributes: TouchAttributes::default().or_defaults(),
             active: false,
             pending: None,
         }
@@ -52,26 +87,57 @@ impl Touch {
         // Provide a stable contact area + orientation; some stacks rely on this.
         touch_info.touchMask =
             WindowsAndMessaging::TOUCH_MASK_CONTACTAREA | WindowsAndMessaging::TOUCH_MASK_ORIENTATION | WindowsAndMessaging::TOUCH_MASK_PRESSURE;
-        let r = 6;
-        touch_info.rcContact.top = self.y - r;
-        touch_info.rcContact.bottom = self.y + r;
-        touch_info.rcContact.left = self.x - r;
-        touch_info.rcContact.right = self.x + r;
-        touch_info.orientation = 90;
-        touch_info.pressure = 32000; // mid-range (0–65535)
+        let attributes = self.attributes;
+        // The contact rectangle is axis-aligned, so the major axis is
+        // laid out vertically and the orientation is reported separately.
+        let half_major = attributes
+            .touch_major
+            .unwrap_or(TouchAttributes::DEFAULT_SIZE)
+            / 2;
+        let half_minor = attributes
+            .touch_minor
+            .unwrap_or(TouchAttributes::DEFAULT_SIZE)
+            / 2;
+        touch_info.rcContact.top = self.y - half_major;
+        touch_info.rcContact.bottom = self.y + half_major;
+        touch_info.rcContact.left = self.x - half_minor;
+        touch_info.rcContact.right = self.x + half_minor;
+        touch_info.orientation = attributes
+            .orientation
+            .unwrap_or(TouchAttributes::DEFAULT_ORIENTATION)
+            .rem_euclid(360) as u32;
+        let pressure = attributes
+            .pressure
+            .unwrap_or(TouchAttributes::DEFAULT_PRESSURE);
+        touch_info.pressure = (pressure.clamp(0.0, 1.0) * 1024.0) as u32;
 
         touch_info
     }
 }
 
+/// The touch contact in `slot`.
+fn touch_slot(touches: &mut [Touch], slot: i32) -> Result<&mut Touch, SimulationError> {
+    usize::try_from(slot)
+        .ok()
+        .and_then(|index| touches.get_mut(index))
+        .ok_or(SimulationError::InvalidSlot(slot))
+}
+
 pub(crate) struct PlatformImpl {
     pen_device: Controls::HSYNTHETICPOINTERDEVICE,
     touches: Arc<Mutex<[Touch; 10]>>,
-    last_pressure: f64,
+    /// The last state of the pen while it was in range.
+    pen: Option<PenState>,
+    devices: HashSet<DeviceClass>,
 }
 
+// SAFETY: The synthetic pointer device handle is not tied to the
+// thread that created it, and the simulator only ever uses it from
+// one thread at a time.
+unsafe impl Send for PlatformImpl {}
+
 impl PlatformImpl {
-    pub(crate) fn new() -> Result<Self, SimulationError> {
+    pub(crate) fn new(builder: &InputSimulatorBuilder) -> Result<Self, SimulationError> {
         unsafe {
             HiDpi::SetProcessDpiAwareness(HiDpi::PROCESS_PER_MONITOR_DPI_AWARE)?;
             Pointer::InitializeTouchInjection(10, Pointer::TOUCH_FEEDBACK_DEFAULT)?;
@@ -172,11 +238,23 @@ impl PlatformImpl {
                 )?
             },
             touches,
-            last_pressure: 0.0,
+            pen: None,
+            devices: builder.devices.clone(),
         })
     }
 
-    pub(crate) fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn require(&self, device: DeviceClass) -> Result<(), SimulationError> {
+        if self.devices.contains(&device) {
+            Ok(())
+        } else {
+            Err(SimulationError::DeviceDisabled(device))
+        }
+    }
+}
+
+impl InputBackend for PlatformImpl {
+    fn move_mouse_abs(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let mut input = KeyboardAndMouse::INPUT {
             r#type: KeyboardAndMouse::INPUT_MOUSE,
             Anonymous: unsafe { std::mem::zeroed() },
@@ -199,7 +277,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn move_mouse_rel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let mut input = KeyboardAndMouse::INPUT {
             r#type: KeyboardAndMouse::INPUT_MOUSE,
             Anonymous: unsafe { std::mem::zeroed() },
@@ -217,103 +296,36 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn left_mouse_down(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
-        };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_LEFTDOWN;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
-        Ok(())
-    }
-
-    pub(crate) fn middle_mouse_down(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
-        };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_MIDDLEDOWN;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
-        Ok(())
-    }
-
-    pub(crate) fn right_mouse_down(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
-        };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_RIGHTDOWN;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
-        Ok(())
-    }
-
-    pub(crate) fn left_mouse_up(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
-        };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_LEFTUP;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
-        Ok(())
-    }
-
-    pub(crate) fn middle_mouse_up(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
+    fn mouse_down(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
+        let (flags, data) = match button {
+            MouseButton::Left => (KeyboardAndMouse::MOUSEEVENTF_LEFTDOWN, 0),
+            MouseButton::Middle => (KeyboardAndMouse::MOUSEEVENTF_MIDDLEDOWN, 0),
+            MouseButton::Right => (KeyboardAndMouse::MOUSEEVENTF_RIGHTDOWN, 0),
+            MouseButton::Side => (KeyboardAndMouse::MOUSEEVENTF_XDOWN, XBUTTON1),
+            MouseButton::Extra => (KeyboardAndMouse::MOUSEEVENTF_XDOWN, XBUTTON2),
+            _ => return Err(SimulationError::UnsupportedButton(button)),
         };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_MIDDLEUP;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
+        send_mouse_button(flags, data);
         Ok(())
     }
 
-    pub(crate) fn right_mouse_up(&mut self) -> Result<(), SimulationError> {
-        let mut input = KeyboardAndMouse::INPUT {
-            r#type: KeyboardAndMouse::INPUT_MOUSE,
-            Anonymous: unsafe { std::mem::zeroed() },
+    fn mouse_up(&mut self, button: MouseButton) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
+        let (flags, data) = match button {
+            MouseButton::Left => (KeyboardAndMouse::MOUSEEVENTF_LEFTUP, 0),
+            MouseButton::Middle => (KeyboardAndMouse::MOUSEEVENTF_MIDDLEUP, 0),
+            MouseButton::Right => (KeyboardAndMouse::MOUSEEVENTF_RIGHTUP, 0),
+            MouseButton::Side => (KeyboardAndMouse::MOUSEEVENTF_XUP, XBUTTON1),
+            MouseButton::Extra => (KeyboardAndMouse::MOUSEEVENTF_XUP, XBUTTON2),
+            _ => return Err(SimulationError::UnsupportedButton(button)),
         };
-        input.Anonymous.mi.dwFlags = KeyboardAndMouse::MOUSEEVENTF_RIGHTUP;
-
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[input],
-                std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
-            );
-        }
+        send_mouse_button(flags, data);
         Ok(())
     }
 
-    pub(crate) fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn wheel(&mut self, x: i32, y: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Mouse)?;
         let mut input_vertical = KeyboardAndMouse::INPUT {
             r#type: KeyboardAndMouse::INPUT_MOUSE,
             Anonymous: unsafe { std::mem::zeroed() },
@@ -337,7 +349,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+    fn key_down(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Keyboard)?;
         let mut input = KeyboardAndMouse::INPUT {
             r#type: KeyboardAndMouse::INPUT_KEYBOARD,
             Anonymous: unsafe { std::mem::zeroed() },
@@ -356,7 +369,8 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+    fn key_up(&mut self, key: Key) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Keyboard)?;
         let mut input = KeyboardAndMouse::INPUT {
             r#type: KeyboardAndMouse::INPUT_KEYBOARD,
             Anonymous: unsafe { std::mem::zeroed() },
@@ -378,74 +392,113 @@ impl PlatformImpl {
 
     // -------- TOUCH API (no direct injection here) --------
 
-    pub fn touch_down(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn touch_down(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
         let mut t = self.touches.lock().unwrap();
-        let idx = slot as usize;
-        t[idx].x = x;
-        t[idx].y = y;
-        t[idx].active = true;
-        t[idx].pending = Some(Pointer::POINTER_FLAG_DOWN);
+        let touch = touch_slot(&mut t[..], slot)?;
+        touch.x = x;
+        touch.y = y;
+        touch.attributes = attributes.or_defaults();
+        touch.active = true;
+        touch.pending = Some(Pointer::POINTER_FLAG_DOWN);
         Ok(())
     }
 
-    pub fn touch_move(&mut self, slot: i32, x: i32, y: i32) -> Result<(), SimulationError> {
+    fn touch_move(
+        &mut self,
+        slot: i32,
+        x: i32,
+        y: i32,
+        attributes: TouchAttributes,
+    ) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
         let mut t = self.touches.lock().unwrap();
-        let idx = slot as usize;
-        t[idx].x = x;
-        t[idx].y = y;
+        let touch = touch_slot(&mut t[..], slot)?;
+        touch.x = x;
+        touch.y = y;
+        touch.attributes = attributes.or(touch.attributes);
         // Keep contact alive. If the contact was not active yet,
         // mark it active so the worker starts emitting UPDATEs.
-        if !t[idx].active {
-            t[idx].active = true;
+        if !touch.active {
+            touch.active = true;
         }
         // No pending flag needed; worker will emit UPDATE each tick while active.
         Ok(())
     }
 
-    pub fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
+    fn touch_up(&mut self, slot: i32) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Touch)?;
         let mut t = self.touches.lock().unwrap();
-        let idx = slot as usize;
+        let touch = touch_slot(&mut t[..], slot)?;
         // Do not clear `active` yet; the worker will emit one UP and then deactivate.
-        t[idx].pending = Some(Pointer::POINTER_FLAG_UP);
+        touch.pending = Some(Pointer::POINTER_FLAG_UP);
         Ok(())
     }
 
     // ------------------ PEN (unchanged) -------------------
 
-    pub(crate) fn pen(
-        &mut self,
-        x: i32,
-        y: i32,
-        pressure: f64,
-        tilt_x: i32,
-        tilt_y: i32,
-    ) -> Result<(), SimulationError> {
-        let flags = if pressure == 0.0 {
-            Pointer::POINTER_FLAG_UP
-        } else if self.last_pressure == 0.0 {
-            Pointer::POINTER_FLAG_DOWN
-                | Pointer::POINTER_FLAG_INRANGE
-                | Pointer::POINTER_FLAG_INCONTACT
-        } else {
-            Pointer::POINTER_FLAG_UPDATE
-                | Pointer::POINTER_FLAG_INRANGE
-                | Pointer::POINTER_FLAG_INCONTACT
+    /// The second barrel button and the hover distance cannot be
+    /// reported, and are ignored.
+    fn pen(&mut self, state: PenState) -> Result<(), SimulationError> {
+        self.require(DeviceClass::Pen)?;
+        if let Some(last) = self.pen {
+            if state.in_range && last.tool != state.tool {
+                self.pen(PenState {
+                    in_range: false,
+                    ..last
+                })?;
+            }
+        }
+
+        let touching = state.in_range && state.contact;
+        let was_touching = self.pen.is_some_and(|last| last.contact);
+        let mut flags = match (was_touching, touching) {
+            (false, true) => Pointer::POINTER_FLAG_DOWN,
+            (true, false) => Pointer::POINTER_FLAG_UP,
+            _ => Pointer::POINTER_FLAG_UPDATE,
         };
+        if state.in_range {
+            flags |= Pointer::POINTER_FLAG_INRANGE;
+        }
+        if touching {
+            flags |= Pointer::POINTER_FLAG_INCONTACT;
+        }
+
+        let mut pen_flags = WindowsAndMessaging::PEN_FLAG_NONE;
+        if state.barrel {
+            pen_flags |= WindowsAndMessaging::PEN_FLAG_BARREL;
+        }
+        if state.tool == PenTool::Eraser {
+            pen_flags |= match touching {
+                true => WindowsAndMessaging::PEN_FLAG_ERASER,
+                false => WindowsAndMessaging::PEN_FLAG_INVERTED,
+            };
+        }
+        let pressure = if touching { state.pressure } else { 0.0 };
 
         let mut input: Controls::POINTER_TYPE_INFO = unsafe { std::mem::zeroed() };
         input.r#type = WindowsAndMessaging::PT_PEN;
         input.Anonymous.penInfo.pointerInfo.pointerType = WindowsAndMessaging::PT_PEN;
         input.Anonymous.penInfo.pointerInfo.pointerFlags = flags;
+        input.Anonymous.penInfo.penFlags = pen_flags;
         input.Anonymous.penInfo.penMask = WindowsAndMessaging::PEN_MASK_PRESSURE
+            | WindowsAndMessaging::PEN_MASK_ROTATION
             | WindowsAndMessaging::PEN_MASK_TILT_X
             | WindowsAndMessaging::PEN_MASK_TILT_Y;
-        input.Anonymous.penInfo.pointerInfo.ptPixelLocation.x = x;
-        input.Anonymous.penInfo.pointerInfo.ptPixelLocation.y = y;
-        input.Anonymous.penInfo.pressure = (pressure * 1024.0) as u32;
-        input.Anonymous.penInfo.tiltX = tilt_x;
-        input.Anonymous.penInfo.tiltY = tilt_y;
+        input.Anonymous.penInfo.pointerInfo.ptPixelLocation.x = state.x;
+        input.Anonymous.penInfo.pointerInfo.ptPixelLocation.y = state.y;
+        input.Anonymous.penInfo.pressure = (pressure.clamp(0.0, 1.0) * 1024.0) as u32;
+        input.Anonymous.penInfo.rotation = state.rotation.rem_euclid(360) as u32;
+        input.Anonymous.penInfo.tiltX = state.tilt_x.clamp(-90, 90);
+        input.Anonymous.penInfo.tiltY = state.tilt_y.clamp(-90, 90);
 
-        self.last_pressure = pressure;
+        self.pen = state.in_range.then_some(state);
 
         unsafe {
             Pointer::InjectSyntheticPointerInput(self.pen_device, &[input])?;
@@ -453,7 +506,7 @@ impl PlatformImpl {
         Ok(())
     }
 
-    pub(crate) fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
+    fn get_screen_size(&self) -> Result<(i32, i32), SimulationError> {
         Ok((
             unsafe {
                 WindowsAndMessaging::GetSystemMetrics(WindowsAndMessaging::SM_CXVIRTUALSCREEN)
@@ -463,6 +516,48 @@ impl PlatformImpl {
             },
         ))
     }
+
+    /// Reports the virtual screen as a single monitor, which starts
+    /// left of or above the origin when a monitor is placed left of
+    /// or above the primary one.
+    fn monitors(&self) -> Result<Vec<Monitor>, SimulationError> {
+        let left = unsafe {
+            WindowsAndMessaging::GetSystemMetrics(WindowsAndMessaging::SM_XVIRTUALSCREEN)
+        };
+        let top = unsafe {
+            WindowsAndMessaging::GetSystemMetrics(WindowsAndMessaging::SM_YVIRTUALSCREEN)
+        };
+        Ok(vec![Monitor {
+            x: left,
+            y: top,
+            ..Monitor::whole_screen(self.get_screen_size()?)
+        }])
+    }
+
+    fn mouse_position(&self) -> Result<Option<(i32, i32)>, SimulationError> {
+        let mut point = Foundation::POINT::default();
+        unsafe { WindowsAndMessaging::GetCursorPos(&mut point)? };
+        Ok(Some((point.x, point.y)))
+    }
+
+    /// Reads the asynchronous key state of the mouse buttons, which
+    /// follows the physical buttons even when they are swapped.
+    fn mouse_buttons(&self) -> Result<Option<Vec<MouseButton>>, SimulationError> {
+        let buttons = [
+            (KeyboardAndMouse::VK_LBUTTON, MouseButton::Left),
+            (KeyboardAndMouse::VK_RBUTTON, MouseButton::Right),
+            (KeyboardAndMouse::VK_MBUTTON, MouseButton::Middle),
+            (KeyboardAndMouse::VK_XBUTTON1, MouseButton::Side),
+            (KeyboardAndMouse::VK_XBUTTON2, MouseButton::Extra),
+        ];
+        Ok(Some(
+            buttons
+                .into_iter()
+                .filter(|&(key, _)| unsafe { KeyboardAndMouse::GetAsyncKeyState(key.0 as i32) } < 0)
+                .map(|(_, button)| button)
+                .collect(),
+        ))
+    }
 }
 
 impl Drop for PlatformImpl {
@@ -473,6 +568,26 @@ impl Drop for PlatformImpl {
     }
 }
 
+/// The `mouseData` values that select the side buttons.
+const XBUTTON1: u32 = 0x0001;
+const XBUTTON2: u32 = 0x0002;
+
+fn send_mouse_button(flags: KeyboardAndMouse::MOUSE_EVENT_FLAGS, data: u32) {
+    let mut input = KeyboardAndMouse::INPUT {
+        r#type: KeyboardAndMouse::INPUT_MOUSE,
+        Anonymous: unsafe { std::mem::zeroed() },
+    };
+    input.Anonymous.mi.dwFlags = flags;
+    input.Anonymous.mi.mouseData = data;
+
+    unsafe {
+        KeyboardAndMouse::SendInput(
+            &[input],
+            std::mem::size_of::<KeyboardAndMouse::INPUT>() as i32,
+        );
+    }
+}
+
 impl From<Key> for u16 {
     fn from(key: Key) -> Self {
         match key {
@@ -630,6 +745,7 @@ impl From<Key> for u16 {
             Key::Wlan => 0x114,
             Key::Dvd => 0x115,
             Key::FnEsc => 0x116,
+            Key::Raw(code) => code,
             _ => 0xFF, // Unknown key
         }
     }